@@ -4,7 +4,20 @@ use axum::{
     http::StatusCode,
 };
 use crate::errors::YouTubeError;
-use serde_json::json;
+use crate::store::StoreError;
+use crate::watchlist::WatchError;
+use crate::webhooks::WebhookError;
+use super::types::ErrorDetails;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Body of every error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Machine readable error code, e.g. `not_found` or `rate_limited`
+    pub error: &'static str,
+    pub message: String,
+}
 
 // Add this new struct for API errors
 #[derive(Debug)]
@@ -12,6 +25,8 @@ pub enum ApiError {
     YouTubeError(YouTubeError),
     InvalidRequest(String),
     NotFound(String),
+    Unauthorized(String),
+    Internal(String),
 }
 
 // Implement From conversion
@@ -21,9 +36,45 @@ impl From<YouTubeError> for ApiError {
     }
 }
 
-// Implement IntoResponse for ApiError instead of YouTubeError
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        ApiError::Internal(err.to_string())
+    }
+}
+
+impl From<WatchError> for ApiError {
+    fn from(err: WatchError) -> Self {
+        match err {
+            WatchError::InvalidChannelId(_) => ApiError::InvalidRequest(err.to_string()),
+            WatchError::YouTube(err) => ApiError::YouTubeError(err),
+            WatchError::Store(err) => err.into(),
+        }
+    }
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(err: WebhookError) -> Self {
+        match err {
+            WebhookError::InvalidUrl(_) => ApiError::InvalidRequest(err.to_string()),
+            WebhookError::Store(err) => err.into(),
+        }
+    }
+}
+
+impl From<ApiError> for ErrorDetails {
+    fn from(err: ApiError) -> Self {
+        let (status, error, message) = err.parts();
+        ErrorDetails {
+            status: status.as_u16(),
+            error,
+            message,
+        }
+    }
+}
+
+impl ApiError {
+    /// Status code, machine readable error code and message of this error
+    pub fn parts(&self) -> (StatusCode, &'static str, String) {
         match self {
             ApiError::YouTubeError(err) => {
                 let (status, error_code, message) = match err {
@@ -37,24 +88,23 @@ impl IntoResponse for ApiError {
                     YouTubeError::SubscriptionsPrivate => (StatusCode::FORBIDDEN, "subscriptions_private", "Subscriptions are private"),
                     _ => (StatusCode::INTERNAL_SERVER_ERROR, "unknown_error", "Unknown error occurred"),
                 };
-
-                (status, Json(json!({
-                    "error": error_code,
-                    "message": message
-                }))).into_response()
-            },
-            ApiError::InvalidRequest(msg) => {
-                (StatusCode::BAD_REQUEST, Json(json!({
-                    "error": "invalid_request",
-                    "message": msg
-                }))).into_response()
+                (status, error_code, message.to_string())
             },
-            ApiError::NotFound(msg) => {
-                (StatusCode::NOT_FOUND, Json(json!({
-                    "error": "not_found",
-                    "message": msg
-                }))).into_response()
-            }
+            ApiError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, "invalid_request", msg.clone()),
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "unauthorized", msg.clone()),
+            ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_server_error", msg.clone()),
         }
     }
-}
\ No newline at end of file
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error_code, message) = self.parts();
+
+        (status, Json(ErrorBody {
+            error: error_code,
+            message,
+        })).into_response()
+    }
+}
@@ -1,63 +1,347 @@
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
     Json,
-    response::Html,
-    extract::State,
+    body::Body,
+    response::{Html, IntoResponse, Response},
+    extract::{MatchedPath, Path, Query, Request, State},
+    middleware::{self, Next},
+    http::{header::{ACCEPT, AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LAST_MODIFIED}, HeaderMap, HeaderValue, StatusCode},
 };
-use std::env;
+use std::convert::Infallible;
+use std::pin::pin;
 use std::sync::Arc;
-use crate::youtube::{channels::{get_channel, LookupType as YTLookupType}, playlist_items::get_playlist_items, subscriptions::get_subscriptions, videos::populate_video_stats};
-use crate::youtubei::{resolve_url::{resolve_url, ResolveUrlResult}, browse::enrich_channel_data};
-use super::types::{AppState, ChannelLookupRequest, ChannelLookupResponse, LookupType, PaginatedRequest, PlaylistItemsResponse, SubscriptionsResponse};
-use super::error::ApiError;
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use serde::de::{Deserialize, IntoDeserializer};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use metrics::{counter, gauge, histogram};
+use tracing::{field, info, info_span, warn, Instrument, Span};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use crate::youtube::{channels::LookupType as YTLookupType, pages::{CrawlError, Crawled}};
+use crate::youtubei::resolve_url::ResolveUrlResult;
+use super::types::{AppState, ChannelLookupRequest, ChannelLookupResponse, LookupType, HistoryRequest, HistoryResponse, PaginatedRequest, PlaylistItemsResponse, PurgeResponse, SubscriptionsResponse, WatchEventsQuery, WatchEventsResponse, WatchRequest, WatchlistResponse, WebhookRequest, WebhooksResponse, DeliveriesQuery, DeliveriesResponse, BatchRequest, BatchResponse, BatchResult, BatchOutcome, ErrorDetails, CrawlLine, CrawlResponse, ChannelExportQuery, CrawlExportQuery, PageQuery, HealthResponse, ReadinessQuery, ReadinessResponse, CanaryReport, CanaryCheck};
+use super::error::{ApiError, ErrorBody};
 use crate::errors::YouTubeError;
+use crate::config::{Config, RouteGroup};
+use crate::store::Store;
+use crate::history::{self, ChannelHistory};
+use crate::cache::{CacheAge, Cached, ResponseCache};
+use crate::lookup::{self, Lookup};
+use crate::telemetry;
+use crate::models::{Channel, PlaylistSummary, Subscription, Video, VideoSummary};
+use crate::watchlist::{WatchedChannel, Watchlist};
+use crate::webhooks::{CreatedWebhook, Webhooks};
+use crate::detect::{detect, Detection, Target};
+use crate::export::{self, Export, Format};
 
-const MAX_RESULTS: u32 = 50;
+/// Events returned by default from `/api/watchlist/events`
+const DEFAULT_EVENTS_LIMIT: usize = 100;
 
-#[cfg(test)]
-fn get_api_key() -> String {
-    dotenvy::dotenv().ok();
-    env::var("API_KEY").expect("API_KEY must be set")
+/// Channel IDs the Data API takes in one `channels` call
+const CHANNELS_PER_CALL: usize = 50;
+
+/// Media type of streamed responses, one JSON document per line
+const NDJSON: &str = "application/x-ndjson";
+
+/// `Cache-Control: no-cache` on the request forces every upstream call to be redone
+fn wants_refresh(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+}
+
+/// `Accept: application/x-ndjson` asks for results to be streamed as they are ready
+fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            let essence = media_type.split(';').next().unwrap_or_default();
+            essence.trim().eq_ignore_ascii_case(NDJSON)
+        })
+}
+
+/// Writes each item as one line of JSON as soon as it is ready
+fn ndjson<S, T>(items: S) -> Response
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize,
+{
+    let lines = items.map(|item| {
+        let mut line = serde_json::to_vec(&item).expect("responses are always serializable");
+        line.push(b'\n');
+        Ok::<_, Infallible>(line)
+    });
+
+    ([(CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response()
+}
+
+/// Up to `max_items` lines of a crawl. The status has been sent by the time
+/// an upstream call fails, so the failure becomes the last line.
+fn crawl_lines<T>(items: impl Stream<Item = Result<Crawled<T>, CrawlError>>, max_items: usize) -> impl Stream<Item = CrawlLine<T>> {
+    items.take(max_items).map(|item| match item {
+        Ok(crawled) => CrawlLine::Item(crawled.item),
+        Err(e) => CrawlLine::Failed {
+            error: ApiError::from(e.error).into(),
+            page_token: e.page_token,
+        },
+    })
+}
+
+/// Collects up to `max_items` items of a crawl. Once something has been
+/// fetched, an upstream failure (usually quota running out) still returns
+/// what came before it along with the page to resume from.
+async fn crawl<T>(items: impl Stream<Item = Result<Crawled<T>, CrawlError>>, max_items: usize) -> Result<CrawlResponse<T>, ApiError> {
+    let mut items = pin!(items);
+    let mut collected = Vec::new();
+    // Whether anything is left after what has been collected so far
+    let mut more = true;
+
+    while collected.len() < max_items {
+        let Some(item) = items.next().await else {
+            more = false;
+            break;
+        };
+        match item {
+            Ok(crawled) => {
+                more = !crawled.last;
+                collected.push(crawled.item);
+            }
+            Err(e) if collected.is_empty() => return Err(ApiError::from(e.error)),
+            Err(e) => {
+                return Ok(CrawlResponse {
+                    items: collected,
+                    complete: false,
+                    page_token: e.page_token,
+                    error: Some(ApiError::from(e.error).into()),
+                })
+            }
+        }
+    }
+
+    Ok(CrawlResponse {
+        items: collected,
+        complete: !more,
+        page_token: None,
+        error: None,
+    })
+}
+
+/// `Age` carries how old the oldest cached part of the response is
+fn cache_headers(age: CacheAge) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    match age.oldest() {
+        Some(age) => {
+            headers.insert(AGE, HeaderValue::from(age.as_secs()));
+            headers.insert("x-cache", HeaderValue::from_static("HIT"));
+        }
+        None => {
+            headers.insert("x-cache", HeaderValue::from_static("MISS"));
+        }
+    }
+    headers
+}
+
+/// Whether `If-None-Match` lists `etag`. Weak validators match too, since
+/// the comparison for GET is always the weak one.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
 }
 
-#[cfg(not(test))]
-fn get_api_key() -> String {
-    env::var("API_KEY").expect("API_KEY must be set")
+/// JSON response for the GET routes, which can be bookmarked and cached by
+/// proxies. The `ETag` is a hash of the body and `Last-Modified` is when its
+/// oldest cached part was fetched. A request that already has the body gets
+/// an empty 304 instead.
+fn conditional_json<T: Serialize>(request_headers: &HeaderMap, age: CacheAge, body: &T) -> Response {
+    let body = serde_json::to_vec(body).expect("responses are always serializable");
+    let hash = format!("{:x}", Sha256::digest(&body));
+    let etag = format!("\"{}\"", &hash[..32]);
+    let last_modified: DateTime<Utc> = (SystemTime::now() - age.oldest().unwrap_or_default()).into();
+
+    let mut headers = cache_headers(age);
+    headers.insert(ETAG, HeaderValue::from_str(&etag).expect("hex is a valid header value"));
+    headers.insert(
+        LAST_MODIFIED,
+        HeaderValue::from_str(&last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).expect("dates are valid header values"),
+    );
+
+    if etag_matches(request_headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+    (headers, [(CONTENT_TYPE, "application/json")], body).into_response()
 }
 
-async fn check_channel_status(client: &reqwest::Client, channel_id: &str) -> Result<Json<ChannelLookupResponse>, ApiError> {
-    let api_key = get_api_key();
-    match get_subscriptions(client, channel_id, &api_key, None, 1).await {
-        Err(YouTubeError::AccountTerminated) => {
-            Err(ApiError::NotFound("This channel has been terminated".to_string()))
+async fn check_channel_status(state: &AppState, channel_id: &str, refresh: bool) -> ApiError {
+    match state.lookup.missing_reason(channel_id, refresh).await {
+        YouTubeError::AccountTerminated => {
+            ApiError::NotFound("This channel has been terminated".to_string())
         }
-        Err(YouTubeError::AccountClosed) => {
-            Err(ApiError::NotFound("This channel has been deleted".to_string()))
+        YouTubeError::AccountClosed => {
+            ApiError::NotFound("This channel has been deleted".to_string())
         }
-        _ => Err(ApiError::NotFound("Channel not found".to_string()))
+        _ => ApiError::NotFound("Channel not found".to_string())
     }
 }
 
+async fn enrich_channel(state: &AppState, channel: &mut Channel, refresh: bool, age: &mut CacheAge) {
+    // Try to enrich but continue if it fails
+    match state.lookup.enrichment(channel, refresh).await {
+        Ok(enrichment) => age.track(enrichment).apply(channel),
+        Err(e) => warn!(channel_id = channel.user_id, error = %e, "Failed to enrich channel data"),
+    }
+}
+
+/// Checks whether the channel's handle URL redirects somewhere else
+async fn handle_redirect(state: &AppState, channel: &Channel, refresh: bool, age: &mut CacheAge) -> Result<Option<String>, ApiError> {
+    let Some(handle) = &channel.handle else {
+        return Ok(None);
+    };
+
+    let url = format!("youtube.com/@{}", handle);
+    let resolve_result = state.lookup.resolve_url(url, refresh)
+        .await
+        .map_err(ApiError::YouTubeError)?;
+
+    Ok(match age.track(resolve_result) {
+        ResolveUrlResult::UrlEndpoint { url } => Some(url),
+        ResolveUrlResult::BrowseEndpoint { .. } => None,
+    })
+}
+
+/// Works out what an `AUTO` lookup refers to and the concrete lookup to run
+/// for it. `/c/` URLs are resolved to a channel ID here.
+async fn detect_lookup(state: &AppState, input: &str, refresh: bool, age: &mut CacheAge) -> Result<(LookupType, String, Detection), ApiError> {
+    let detection = detect(input)
+        .ok_or_else(|| ApiError::InvalidRequest("Not a recognized YouTube URL or identifier".to_string()))?;
+
+    let (lookup_type, id) = match &detection.target {
+        Target::ChannelId(channel_id) => (LookupType::ChannelId, channel_id.clone()),
+        Target::Handle(handle) => (LookupType::Handle, handle.clone()),
+        Target::Username(username) => (LookupType::Username, username.clone()),
+        Target::CustomUrl(name) => (LookupType::CustomUrl, name.clone()),
+        Target::Vanity(name) => (LookupType::Vanity, name.clone()),
+        Target::Url(url) => match state.lookup.resolve_url(url.clone(), refresh).await {
+            Ok(cached) => match age.track(cached) {
+                ResolveUrlResult::BrowseEndpoint { browse_id } => (LookupType::ChannelId, browse_id),
+                ResolveUrlResult::UrlEndpoint { .. } => return Err(ApiError::NotFound("Channel URL not found".to_string())),
+            },
+            Err(YouTubeError::NotFound) => return Err(ApiError::NotFound("Channel URL not found".to_string())),
+            Err(e) => return Err(ApiError::YouTubeError(e)),
+        },
+        Target::Video(video_id) => (LookupType::VideoId, video_id.clone()),
+        Target::Playlist(playlist_id) => (LookupType::PlaylistId, playlist_id.clone()),
+    };
+
+    Ok((lookup_type, id, detection))
+}
+
+/// A lookup narrowed down to one of the channel lookup types, along with
+/// what it started from
+struct Resolved {
+    lookup_type: LookupType,
+    id: String,
+    video: Option<VideoSummary>,
+    playlist: Option<PlaylistSummary>,
+    interpretation: Option<Detection>,
+}
+
+/// Detects what an `AUTO` lookup refers to, and turns video and playlist
+/// lookups into a lookup of the channel that owns them
+async fn resolve_lookup(state: &AppState, lookup_type: LookupType, id: String, refresh: bool, age: &mut CacheAge) -> Result<Resolved, ApiError> {
+    let (lookup_type, id, interpretation) = match lookup_type {
+        LookupType::Auto => {
+            let (lookup_type, id, detection) = detect_lookup(state, &id, refresh, age).await?;
+            (lookup_type, id, Some(detection))
+        }
+        lookup_type => (lookup_type, id, None),
+    };
+
+    let (lookup_type, id, video, playlist) = match lookup_type {
+        LookupType::VideoId => {
+            let video = match state.lookup.video(&id, refresh).await {
+                Ok(cached) => age.track(cached),
+                Err(YouTubeError::NotFound) => return Err(ApiError::NotFound("Video not found".to_string())),
+                Err(e) => return Err(ApiError::YouTubeError(e)),
+            };
+            (LookupType::ChannelId, video.channel_id.clone(), Some(video), None)
+        }
+        LookupType::PlaylistId => {
+            let playlist = match state.lookup.playlist(&id, refresh).await {
+                Ok(cached) => age.track(cached),
+                Err(YouTubeError::NotFound) => return Err(ApiError::NotFound("Playlist not found".to_string())),
+                Err(e) => return Err(ApiError::YouTubeError(e)),
+            };
+            (LookupType::ChannelId, playlist.channel_id.clone(), None, Some(playlist))
+        }
+        lookup_type => (lookup_type, id, None, None),
+    };
+
+    Ok(Resolved { lookup_type, id, video, playlist, interpretation })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/channel",
+    tag = "channels",
+    request_body = ChannelLookupRequest,
+    params(("Cache-Control" = Option<String>, Header, description = "`no-cache` redoes every upstream call")),
+    responses(
+        (status = 200, description = "The channel, with `Age` and `X-Cache` headers", body = ChannelLookupResponse),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
 async fn channel_handler(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     payload: Result<Json<ChannelLookupRequest>, axum::extract::rejection::JsonRejection>,
-) -> Result<Json<ChannelLookupResponse>, ApiError> {
+) -> Result<(HeaderMap, Json<ChannelLookupResponse>), ApiError> {
     let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
 
-    let (channel, redirect_url) = match payload.r#type {
+    let response = lookup_channel(&state, payload, wants_refresh(&headers), &mut age).await?;
+
+    Ok((cache_headers(age), Json(response)))
+}
+
+/// Looks up one channel along with its InnerTube-only fields and handle
+/// redirect, and records a history snapshot of it
+#[tracing::instrument(
+    name = "lookup",
+    skip_all,
+    fields(lookup_type = ?request.r#type, id = %request.id, refresh = refresh, channel_id = field::Empty),
+)]
+async fn lookup_channel(state: &AppState, request: ChannelLookupRequest, refresh: bool, age: &mut CacheAge) -> Result<ChannelLookupResponse, ApiError> {
+    let Resolved { lookup_type, id, video, playlist, interpretation } =
+        resolve_lookup(state, request.r#type, request.id, refresh, age).await?;
+
+    let (channel, redirect_url) = match lookup_type {
         LookupType::CustomUrl => {
             // First get channel from +URL
-            let plus_url = format!("youtube.com/+{}", payload.id);
-            let plus_resolve_result = match resolve_url(&state.client, plus_url).await {
-                Ok(Some(result)) => result,
-                Ok(None) => return Err(ApiError::NotFound("Custom URL not found".to_string())),
+            let plus_url = format!("youtube.com/+{}", id);
+            let plus_resolve_result = match state.lookup.resolve_url(plus_url, refresh).await {
+                Ok(cached) => age.track(cached),
                 Err(YouTubeError::NotFound) => return Err(ApiError::NotFound("Custom URL not found".to_string())),
                 Err(e) => return Err(ApiError::YouTubeError(e)),
             };
 
-            println!("check: {:?}", plus_resolve_result);
-
             let browse_id = match plus_resolve_result {
                 ResolveUrlResult::BrowseEndpoint { browse_id } => browse_id,
                 ResolveUrlResult::UrlEndpoint { .. } => {
@@ -65,221 +349,810 @@ async fn channel_handler(
                 }
             };
 
-            let api_key = get_api_key();
-            let mut channel = get_channel(
-                &state.client,
-                YTLookupType::ChannelID(browse_id),
-                &api_key,
-            ).await?;
-
-            // Try to enrich but continue if it fails
-            if let Err(e) = enrich_channel_data(&state.client, &mut channel).await {
-                eprintln!("Failed to enrich channel data for {}: {:?}", channel.user_id, e);
-            }
+            let mut channel = age.track(state.lookup.channel(YTLookupType::ChannelID(browse_id), refresh).await?);
+            enrich_channel(state, &mut channel, refresh, age).await;
 
             // Then check non-plus URL for redirect
-            let url = format!("youtube.com/{}", payload.id.to_uppercase());
-            let resolve_result = resolve_url(&state.client, url)
+            let url = format!("youtube.com/{}", id.to_uppercase());
+            let resolve_result = state.lookup.resolve_url(url, refresh)
                 .await
-                .map_err(|e| ApiError::YouTubeError(e))?;
+                .map_err(ApiError::YouTubeError)?;
 
-            let redirect_url = match resolve_result {
-                Some(ResolveUrlResult::UrlEndpoint { url }) => Some(url),
-                _ => None,
+            let redirect_url = match age.track(resolve_result) {
+                ResolveUrlResult::UrlEndpoint { url } => Some(url),
+                ResolveUrlResult::BrowseEndpoint { .. } => None,
             };
 
             (channel, redirect_url)
         }
         LookupType::Vanity => {
             // Get the main vanity URL channel first
-            let url = format!("youtube.com/{}", payload.id.to_uppercase());
-            let resolve_result = resolve_url(&state.client, url)
+            let url = format!("youtube.com/{}", id.to_uppercase());
+            let resolve_result = state.lookup.resolve_url(url, refresh)
                 .await
-                .map_err(|e| ApiError::YouTubeError(e))?;
+                .map_err(ApiError::YouTubeError)?;
 
-            let main_channel_id = match resolve_result {
-                Some(ResolveUrlResult::BrowseEndpoint { browse_id }) => browse_id,
-                _ => return Err(ApiError::NotFound("Invalid vanity URL".to_string())),
+            let main_channel_id = match age.track(resolve_result) {
+                ResolveUrlResult::BrowseEndpoint { browse_id } => browse_id,
+                ResolveUrlResult::UrlEndpoint { .. } => return Err(ApiError::NotFound("Invalid vanity URL".to_string())),
             };
 
-            // Check +URL, but only error if it points to the same channel
-            let plus_url = format!("youtube.com/+{}", payload.id);
-            if let Ok(Some(ResolveUrlResult::BrowseEndpoint { browse_id })) = resolve_url(&state.client, plus_url).await {
-                if browse_id == main_channel_id {
-                    return Err(ApiError::NotFound("Not a vanity URL".to_string()));
-                }
-            }
-
-            // Check /user/, but only error if it points to the same channel
-            let user_url = format!("youtube.com/user/{}", payload.id);
-            if let Ok(Some(ResolveUrlResult::BrowseEndpoint { browse_id })) = resolve_url(&state.client, user_url).await {
-                if browse_id == main_channel_id {
-                    return Err(ApiError::NotFound("Not a vanity URL".to_string()));
+            // Check +URL and /user/, but only error if they point to the same channel
+            for url in [format!("youtube.com/+{}", id), format!("youtube.com/user/{}", id)] {
+                if let Ok(cached) = state.lookup.resolve_url(url, refresh).await {
+                    if let ResolveUrlResult::BrowseEndpoint { browse_id } = age.track(cached) {
+                        if browse_id == main_channel_id {
+                            return Err(ApiError::NotFound("Not a vanity URL".to_string()));
+                        }
+                    }
                 }
             }
 
             // If we get here, it's a valid vanity URL - return the channel
-            let api_key = get_api_key();
-            let mut channel = get_channel(
-                &state.client,
-                YTLookupType::ChannelID(main_channel_id),
-                &api_key,
-            ).await?;
-
-            // Try to enrich but continue if it fails
-            if let Err(e) = enrich_channel_data(&state.client, &mut channel).await {
-                eprintln!("Failed to enrich channel data for {}: {:?}", channel.user_id, e);
-            }
+            let mut channel = age.track(state.lookup.channel(YTLookupType::ChannelID(main_channel_id), refresh).await?);
+            enrich_channel(state, &mut channel, refresh, age).await;
 
             (channel, None)
         }
         LookupType::Username => {
-            let api_key = get_api_key();
-            let mut channel = get_channel(
-                &state.client,
-                YTLookupType::Username(payload.id.clone()),
-                &api_key,
-            ).await?;
-
-            // Try to enrich but continue if it fails
-            if let Err(e) = enrich_channel_data(&state.client, &mut channel).await {
-                eprintln!("Failed to enrich channel data for {}: {:?}", channel.user_id, e);
-            }
-
-            let mut redirect_url = None;
-            if let Some(handle) = channel.handle.clone() {
-                let url = format!("youtube.com/@{}", handle);
-                let resolve_result = resolve_url(&state.client, url)
-                    .await
-                    .map_err(|e| ApiError::YouTubeError(e))?;
-
-                redirect_url = match resolve_result {
-                    Some(ResolveUrlResult::UrlEndpoint { url }) => Some(url),
-                    _ => None,
-                };
-            }
+            let mut channel = age.track(state.lookup.channel(YTLookupType::Username(id.clone()), refresh).await?);
+            enrich_channel(state, &mut channel, refresh, age).await;
 
+            let redirect_url = handle_redirect(state, &channel, refresh, age).await?;
             (channel, redirect_url)
         }
         LookupType::Handle => {
-            let api_key = get_api_key();
-            let mut channel = get_channel(
-                &state.client,
-                YTLookupType::Handle(payload.id.clone()),
-                &api_key,
-            ).await?;
-
-            // Try to enrich but continue if it fails
-            if let Err(e) = enrich_channel_data(&state.client, &mut channel).await {
-                eprintln!("Failed to enrich channel data for {}: {:?}", channel.user_id, e);
-            }
-
-            let mut redirect_url = None;
-            if let Some(handle) = channel.handle.clone() {
-                let url = format!("youtube.com/@{}", handle);
-                let resolve_result = resolve_url(&state.client, url)
-                    .await
-                    .map_err(|e| ApiError::YouTubeError(e))?;
-
-                redirect_url = match resolve_result {
-                    Some(ResolveUrlResult::UrlEndpoint { url }) => Some(url),
-                    _ => None,
-                };
-            }
+            let mut channel = age.track(state.lookup.channel(YTLookupType::Handle(id.clone()), refresh).await?);
+            enrich_channel(state, &mut channel, refresh, age).await;
 
+            let redirect_url = handle_redirect(state, &channel, refresh, age).await?;
             (channel, redirect_url)
         }
         LookupType::ChannelId => {
-            let api_key = get_api_key();
-            let channel_result = get_channel(
-                &state.client,
-                YTLookupType::ChannelID(payload.id.clone()),
-                &api_key,
-            ).await;
+            let channel_result = state.lookup.channel(YTLookupType::ChannelID(id.clone()), refresh).await;
 
             let mut channel = match channel_result {
-                Ok(channel) => channel,
+                Ok(channel) => age.track(channel),
                 Err(YouTubeError::NotFound) => {
-                    return check_channel_status(&state.client, &payload.id).await;
+                    return Err(check_channel_status(state, &id, refresh).await);
                 }
                 Err(e) => return Err(ApiError::YouTubeError(e)),
             };
+            enrich_channel(state, &mut channel, refresh, age).await;
 
-            if let Err(e) = enrich_channel_data(&state.client, &mut channel).await {
-                eprintln!("Failed to enrich channel data for {}: {:?}", channel.user_id, e);
-            }
+            let redirect_url = handle_redirect(state, &channel, refresh, age).await?;
+            (channel, redirect_url)
+        }
+        lookup_type @ (LookupType::Auto | LookupType::VideoId | LookupType::PlaylistId) => {
+            return Err(ApiError::Internal(format!("{:?} lookup was not resolved to a channel", lookup_type)));
+        }
+    };
 
-            let mut redirect_url = None;
-            if let Some(handle) = channel.handle.clone() {
-                let url = format!("youtube.com/@{}", handle);
-                let resolve_result = resolve_url(&state.client, url)
-                    .await
-                    .map_err(|e| ApiError::YouTubeError(e))?;
+    Span::current().record("channel_id", channel.user_id.as_str());
+    if let Err(e) = state.history.record(&channel) {
+        warn!(channel_id = channel.user_id, error = %e, "Failed to record snapshot");
+    }
 
-                redirect_url = match resolve_result {
-                    Some(ResolveUrlResult::UrlEndpoint { url }) => Some(url),
-                    _ => None,
-                };
-            }
+    Ok(ChannelLookupResponse {
+        channel,
+        redirect_url,
+        video,
+        playlist,
+        interpretation,
+    })
+}
 
-            (channel, redirect_url)
+/// Works out the channel ID behind one batch input. Name based lookups go
+/// through `resolve_url`, which costs no quota, and only handles and
+/// usernames it cannot resolve fall back to the Data API. The channel comes
+/// along when that fallback already fetched it.
+async fn resolve_batch_item(
+    state: &AppState,
+    request: ChannelLookupRequest,
+    refresh: bool,
+    age: &mut CacheAge,
+) -> Result<(Resolved, Option<Cached<Channel>>), ApiError> {
+    let mut resolved = resolve_lookup(state, request.r#type, request.id, refresh, age).await?;
+
+    let url = match resolved.lookup_type {
+        LookupType::ChannelId => return Ok((resolved, None)),
+        LookupType::Handle => format!("youtube.com/@{}", resolved.id.trim_start_matches('@')),
+        LookupType::Username => format!("youtube.com/user/{}", resolved.id),
+        LookupType::CustomUrl => format!("youtube.com/+{}", resolved.id),
+        LookupType::Vanity => format!("youtube.com/{}", resolved.id.to_uppercase()),
+        lookup_type @ (LookupType::Auto | LookupType::VideoId | LookupType::PlaylistId) => {
+            return Err(ApiError::Internal(format!("{:?} lookup was not resolved to a channel", lookup_type)));
+        }
+    };
+
+    let browse_id = match state.lookup.resolve_url(url, refresh).await {
+        Ok(cached) => match age.track(cached) {
+            ResolveUrlResult::BrowseEndpoint { browse_id } => Some(browse_id),
+            ResolveUrlResult::UrlEndpoint { .. } => None,
+        },
+        Err(YouTubeError::NotFound) => None,
+        Err(e) => return Err(ApiError::YouTubeError(e)),
+    };
+
+    let (channel_id, channel) = match (browse_id, &resolved.lookup_type) {
+        (Some(browse_id), _) => (browse_id, None),
+        (None, LookupType::Handle) => {
+            let channel = state.lookup.channel(YTLookupType::Handle(resolved.id.clone()), refresh).await?;
+            (channel.value.user_id.clone(), Some(channel))
         }
+        (None, LookupType::Username) => {
+            let channel = state.lookup.channel(YTLookupType::Username(resolved.id.clone()), refresh).await?;
+            (channel.value.user_id.clone(), Some(channel))
+        }
+        (None, _) => return Err(ApiError::NotFound("Channel not found".to_string())),
+    };
+
+    resolved.lookup_type = LookupType::ChannelId;
+    resolved.id = channel_id;
+    Ok((resolved, channel))
+}
+
+/// Enriches one channel fetched for a batch, the same way `/api/channel` does
+async fn complete_batch_item(
+    state: &AppState,
+    resolved: Resolved,
+    channel: Result<Cached<Channel>, YouTubeError>,
+    refresh: bool,
+    age: &mut CacheAge,
+) -> Result<ChannelLookupResponse, ApiError> {
+    let mut channel = match channel {
+        Ok(cached) => age.track(cached),
+        Err(YouTubeError::NotFound) => return Err(check_channel_status(state, &resolved.id, refresh).await),
+        Err(e) => return Err(ApiError::YouTubeError(e)),
     };
+    enrich_channel(state, &mut channel, refresh, age).await;
+    let redirect_url = handle_redirect(state, &channel, refresh, age).await?;
+
+    if let Err(e) = state.history.record(&channel) {
+        warn!(channel_id = channel.user_id, error = %e, "Failed to record snapshot");
+    }
 
-    Ok(Json(ChannelLookupResponse {
+    Ok(ChannelLookupResponse {
         channel,
         redirect_url,
-    }))
+        video: resolved.video,
+        playlist: resolved.playlist,
+        interpretation: resolved.interpretation,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/channels/batch",
+    tag = "channels",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "One result per input, streamed one per line with `Accept: application/x-ndjson`", content(
+            (BatchResponse = "application/json"),
+            (BatchResult = "application/x-ndjson"),
+        )),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+    ),
+)]
+async fn batch_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Result<Json<BatchRequest>, axum::extract::rejection::JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let limits = state.limits.clone();
+    if payload.items.is_empty() || payload.items.len() > limits.max_batch_size {
+        return Err(ApiError::InvalidRequest(format!("A batch takes between 1 and {} items", limits.max_batch_size)));
+    }
+    let refresh = wants_refresh(&headers);
+    let stream_results = wants_ndjson(&headers);
+    let mut age = CacheAge::default();
+    let inputs: Vec<String> = payload.items.iter().map(|item| item.id.clone()).collect();
+
+    let resolved: Vec<_> = stream::iter(payload.items)
+        .map(|item| {
+            let state = &state;
+            async move {
+                let mut age = CacheAge::default();
+                (resolve_batch_item(state, item, refresh, &mut age).await, age)
+            }
+        })
+        .buffered(limits.batch_concurrency)
+        .collect()
+        .await;
+
+    // Channels not fetched while resolving are fetched in as few Data API
+    // calls as possible. A failed call only fails the inputs it was for.
+    let channel_ids: Vec<String> = resolved
+        .iter()
+        .filter_map(|(result, _)| match result {
+            Ok((resolved, None)) => Some(resolved.id.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut fetched = Vec::with_capacity(channel_ids.len());
+    for chunk in channel_ids.chunks(CHANNELS_PER_CALL) {
+        match state.lookup.channels(chunk, refresh).await {
+            Ok(channels) => fetched.extend(channels.into_iter().map(Ok)),
+            Err(e) => {
+                let error = ErrorDetails::from(ApiError::YouTubeError(e));
+                fetched.extend(chunk.iter().map(|_| Err(error.clone())));
+            }
+        }
+    }
+    let mut fetched = fetched.into_iter();
+
+    let items: Vec<_> = resolved
+        .into_iter()
+        .map(|(result, item_age)| {
+            age.merge(item_age);
+            match result.map_err(ErrorDetails::from)? {
+                (resolved, Some(channel)) => Ok((resolved, Ok(channel))),
+                (resolved, None) => match fetched.next() {
+                    Some(channel) => Ok((resolved, channel?)),
+                    None => Err(ErrorDetails::from(ApiError::Internal(format!("No channel was fetched for {}", resolved.id)))),
+                },
+            }
+        })
+        .collect();
+
+    let completed = stream::iter(inputs.into_iter().zip(items))
+        .map(move |(id, item)| {
+            let state = state.clone();
+            async move {
+                let mut age = CacheAge::default();
+                let outcome = match item {
+                    Ok((resolved, channel)) => match complete_batch_item(&state, resolved, channel, refresh, &mut age).await {
+                        Ok(response) => BatchOutcome::Found(Box::new(response)),
+                        Err(e) => BatchOutcome::Failed { error: e.into() },
+                    },
+                    Err(error) => BatchOutcome::Failed { error },
+                };
+                (BatchResult { id, outcome }, age)
+            }
+        })
+        .buffered(limits.batch_concurrency);
+
+    if stream_results {
+        return Ok(ndjson(completed.map(|(result, _)| result)));
+    }
+
+    let results = completed
+        .map(|(result, item_age)| {
+            age.merge(item_age);
+            result
+        })
+        .collect()
+        .await;
+
+    Ok((cache_headers(age), Json(BatchResponse { results })).into_response())
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/videos",
+    tag = "crawls",
+    request_body = PaginatedRequest,
+    responses(
+        (status = 200, description = "One page of a playlist, a `CrawlResponse_Video` with `all` set, or one line per video with `Accept: application/x-ndjson`", content(
+            (PlaylistItemsResponse = "application/json"),
+            (CrawlLine<Video> = "application/x-ndjson"),
+        )),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
 async fn videos_handler(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     payload: Result<Json<PaginatedRequest>, axum::extract::rejection::JsonRejection>,
-) -> Result<Json<PlaylistItemsResponse>, ApiError> {
+) -> Result<Response, ApiError> {
     let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
 
-    let api_key = get_api_key();
-    
-    // First get playlist items
-    let (mut items, page_token) = get_playlist_items(
-        &state.client,
+    // Every page from `page_token` on, streamed or collected
+    let max_items = state.limits.crawl_items(payload.max_items);
+    if wants_ndjson(&headers) {
+        let videos = state.lookup.all_playlist_items(payload.id, payload.page_token);
+        return Ok(ndjson(crawl_lines(videos, max_items)));
+    }
+    if payload.all {
+        let videos = state.lookup.all_playlist_items(payload.id, payload.page_token);
+        return Ok(Json(crawl(videos, max_items).await?).into_response());
+    }
+
+    // Playlist items with their video stats populated
+    let (items, page_token) = age.track(state.lookup.playlist_items(
         &payload.id,
-        &api_key,
         payload.page_token.as_deref(),
-        MAX_RESULTS,
-    ).await?;
-
-    // Then populate with video stats
-    populate_video_stats(
-        &state.client,
-        &mut items,
-        &api_key,
-    ).await?;
+        wants_refresh(&headers),
+    ).await?);
 
-    Ok(Json(PlaylistItemsResponse {
+    Ok((cache_headers(age), Json(PlaylistItemsResponse {
         items,
         page_token,
-    }))
+    })).into_response())
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/subscriptions",
+    tag = "crawls",
+    request_body = PaginatedRequest,
+    responses(
+        (status = 200, description = "One page of subscriptions, a `CrawlResponse_Subscription` with `all` set, or one line per subscription with `Accept: application/x-ndjson`", content(
+            (SubscriptionsResponse = "application/json"),
+            (CrawlLine<Subscription> = "application/x-ndjson"),
+        )),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
 async fn subscriptions_handler(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     payload: Result<Json<PaginatedRequest>, axum::extract::rejection::JsonRejection>,
-) -> Result<Json<SubscriptionsResponse>, ApiError> {
+) -> Result<Response, ApiError> {
     let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
+
+    let max_items = state.limits.crawl_items(payload.max_items);
+    if wants_ndjson(&headers) {
+        let subscriptions = state.lookup.all_subscriptions(payload.id, payload.page_token);
+        return Ok(ndjson(crawl_lines(subscriptions, max_items)));
+    }
+    if payload.all {
+        let subscriptions = state.lookup.all_subscriptions(payload.id, payload.page_token);
+        return Ok(Json(crawl(subscriptions, max_items).await?).into_response());
+    }
 
-    let api_key = get_api_key();
-    let (items, page_token) = get_subscriptions(
-        &state.client,
+    let (items, page_token) = age.track(state.lookup.subscriptions(
         &payload.id,
-        &api_key,
         payload.page_token.as_deref(),
-        MAX_RESULTS,
-    ).await?;
+        wants_refresh(&headers),
+    ).await?);
 
-    Ok(Json(SubscriptionsResponse {
+    Ok((cache_headers(age), Json(SubscriptionsResponse {
         items,
         page_token,
+    })).into_response())
+}
+
+/// Lookup types in paths are case insensitive and may use dashes, e.g.
+/// `/api/channels/channel-id/UC...`
+fn path_lookup_type(lookup_type: &str) -> Result<LookupType, ApiError> {
+    let lookup_type = lookup_type.to_ascii_uppercase().replace('-', "_");
+    LookupType::deserialize(lookup_type.as_str().into_deserializer())
+        .map_err(|e: serde::de::value::Error| ApiError::InvalidRequest(e.to_string()))
+}
+
+/// The uploads playlist of a channel shares its ID after the first two letters
+fn uploads_playlist(channel_id: &str) -> Result<String, ApiError> {
+    channel_id
+        .strip_prefix("UC")
+        .map(|rest| format!("UU{}", rest))
+        .ok_or_else(|| ApiError::InvalidRequest("Expected a channel ID starting with UC".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/channels/{type}/{id}",
+    tag = "channels",
+    params(
+        ("type" = String, Path, description = "A lookup type, case insensitive, e.g. `handle` or `channel-id`"),
+        ("id" = String, Path),
+        ("If-None-Match" = Option<String>, Header),
+    ),
+    responses(
+        (status = 200, description = "The channel, with `ETag` and `Last-Modified` headers", body = ChannelLookupResponse),
+        (status = 304, description = "Unchanged since the `ETag` in `If-None-Match`"),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn get_channel_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    path: Result<Path<(String, String)>, axum::extract::rejection::PathRejection>,
+) -> Result<Response, ApiError> {
+    let Path((lookup_type, id)) = path.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
+
+    let request = ChannelLookupRequest { r#type: path_lookup_type(&lookup_type)?, id };
+    let response = lookup_channel(&state, request, wants_refresh(&headers), &mut age).await?;
+
+    Ok(conditional_json(&headers, age, &response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/channels/{id}/videos",
+    tag = "crawls",
+    params(("id" = String, Path, description = "Channel ID"), PageQuery),
+    responses(
+        (status = 200, description = "One page of the channel's uploads", body = PlaylistItemsResponse),
+        (status = 304, description = "Unchanged since the `ETag` in `If-None-Match`"),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn get_videos_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    query: Result<Query<PageQuery>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
+
+    let (items, page_token) = age.track(state.lookup.playlist_items(
+        &uploads_playlist(&channel_id)?,
+        query.page_token.as_deref(),
+        wants_refresh(&headers),
+    ).await?);
+
+    Ok(conditional_json(&headers, age, &PlaylistItemsResponse { items, page_token }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/channels/{id}/subscriptions",
+    tag = "crawls",
+    params(("id" = String, Path, description = "Channel ID"), PageQuery),
+    responses(
+        (status = 200, description = "One page of the channel's subscriptions", body = SubscriptionsResponse),
+        (status = 304, description = "Unchanged since the `ETag` in `If-None-Match`"),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn get_subscriptions_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+    query: Result<Query<PageQuery>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
+
+    let (items, page_token) = age.track(state.lookup.subscriptions(
+        &channel_id,
+        query.page_token.as_deref(),
+        wants_refresh(&headers),
+    ).await?);
+
+    Ok(conditional_json(&headers, age, &SubscriptionsResponse { items, page_token }))
+}
+
+/// A downloadable export named after what it holds, e.g. `videos-PL123.csv`
+fn export_response(name: &str, id: &str, format: Format, body: Vec<u8>) -> Response {
+    // IDs can come from pasted URLs, so only safe characters make it into the file name
+    let id: String = id.chars().filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')).collect();
+    let disposition = format!("attachment; filename=\"{}-{}.{}\"", name, id, format.extension());
+
+    ([(CONTENT_TYPE, format.content_type()), (CONTENT_DISPOSITION, disposition.as_str())], body).into_response()
+}
+
+/// Exports whatever a crawl fetched. A crawl cut short still downloads, with
+/// headers saying why and which page to resume from.
+fn crawl_export<T: Export>(name: &str, id: &str, format: Format, crawl: CrawlResponse<T>) -> Response {
+    let mut response = export_response(name, id, format, export::write(&crawl.items, format));
+
+    let headers = response.headers_mut();
+    headers.insert("x-export-complete", HeaderValue::from_static(if crawl.complete { "true" } else { "false" }));
+    if let Some(page_token) = crawl.page_token.and_then(|token| HeaderValue::from_str(&token).ok()) {
+        headers.insert("x-export-page-token", page_token);
+    }
+    if let Some(error) = crawl.error {
+        headers.insert("x-export-error", HeaderValue::from_static(error.error));
+    }
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export/channel",
+    tag = "exports",
+    params(ChannelExportQuery),
+    responses(
+        (status = 200, description = "A one row file", content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn export_channel_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    query: Result<Query<ChannelExportQuery>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let mut age = CacheAge::default();
+
+    let request = ChannelLookupRequest { r#type: query.r#type, id: query.id };
+    let response = lookup_channel(&state, request, wants_refresh(&headers), &mut age).await?;
+    let channel = response.channel;
+
+    let body = export::write(std::slice::from_ref(&channel), query.format);
+    Ok((cache_headers(age), export_response("channel", &channel.user_id, query.format, body)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export/videos",
+    tag = "exports",
+    params(CrawlExportQuery),
+    responses(
+        (status = 200, description = "Every video of a playlist, see `X-Export-Complete`", content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn export_videos_handler(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<CrawlExportQuery>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    let videos = state.lookup.all_playlist_items(query.id.clone(), query.page_token);
+    let crawl = crawl(videos, state.limits.crawl_items(query.max_items)).await?;
+
+    Ok(crawl_export("videos", &query.id, query.format, crawl))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export/subscriptions",
+    tag = "exports",
+    params(CrawlExportQuery),
+    responses(
+        (status = 200, description = "Every subscription of a channel, see `X-Export-Complete`", content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+        (status = "5XX", description = "Upstream or storage failure", body = ErrorBody),
+    ),
+)]
+async fn export_subscriptions_handler(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<CrawlExportQuery>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    let subscriptions = state.lookup.all_subscriptions(query.id.clone(), query.page_token);
+    let crawl = crawl(subscriptions, state.limits.crawl_items(query.max_items)).await?;
+
+    Ok(crawl_export("subscriptions", &query.id, query.format, crawl))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/channel/history",
+    tag = "channels",
+    request_body = HistoryRequest,
+    responses(
+        (status = 200, body = HistoryResponse),
+        (status = 404, description = "The channel has never been looked up", body = ErrorBody),
+    ),
+)]
+async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<HistoryRequest>, axum::extract::rejection::JsonRejection>,
+) -> Result<Json<HistoryResponse>, ApiError> {
+    let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    let snapshots = state.history
+        .snapshots(&payload.id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if snapshots.is_empty() {
+        return Err(ApiError::NotFound("No history for this channel".to_string()));
+    }
+
+    Ok(Json(HistoryResponse {
+        channel_id: payload.id,
+        diffs: history::diffs(&snapshots),
+        snapshots,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/watchlist",
+    tag = "watchlist",
+    responses((status = 200, body = WatchlistResponse)),
+)]
+async fn watchlist_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<WatchlistResponse>, ApiError> {
+    Ok(Json(WatchlistResponse {
+        items: state.watchlist.list()?,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/watchlist",
+    tag = "watchlist",
+    request_body = WatchRequest,
+    responses(
+        (status = 200, body = WatchedChannel),
+        (status = "4XX", description = "Invalid request, not found or out of quota", body = ErrorBody),
+    ),
+)]
+async fn watch_handler(
+    State(state): State<Arc<AppState>>,
+    payload: Result<Json<WatchRequest>, axum::extract::rejection::JsonRejection>,
+) -> Result<Json<WatchedChannel>, ApiError> {
+    let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    Ok(Json(state.watchlist.add(&payload.id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/watchlist/{channel_id}",
+    tag = "watchlist",
+    params(("channel_id" = String, Path)),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 404, description = "Not on the watchlist", body = ErrorBody),
+    ),
+)]
+async fn unwatch_handler(
+    State(state): State<Arc<AppState>>,
+    Path(channel_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.watchlist.remove(&channel_id)? {
+        return Err(ApiError::NotFound("Channel is not on the watchlist".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/watchlist/events",
+    tag = "watchlist",
+    params(WatchEventsQuery),
+    responses((status = 200, body = WatchEventsResponse)),
+)]
+async fn watch_events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WatchEventsQuery>,
+) -> Result<Json<WatchEventsResponse>, ApiError> {
+    let items = state.watchlist.events(
+        query.channel_id.as_deref(),
+        query.limit.unwrap_or(DEFAULT_EVENTS_LIMIT),
+    )?;
+
+    Ok(Json(WatchEventsResponse { items }))
+}
+
+/// Admin routes are only mounted when a token is configured
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // Compared in constant time so response times don't reveal the token
+    match (&state.admin_token, token) {
+        (Some(expected), Some(token)) if bool::from(expected.as_bytes().ct_eq(token.as_bytes())) => Ok(()),
+        _ => Err(ApiError::Unauthorized("Missing or invalid admin token".to_string())),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/cache/{channel_id}",
+    tag = "admin",
+    params(("channel_id" = String, Path)),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = PurgeResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+)]
+async fn purge_cache_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<String>,
+) -> Result<Json<PurgeResponse>, ApiError> {
+    authorize_admin(&state, &headers)?;
+
+    let purged = state.lookup.purge_channel(&channel_id).await.map_err(ApiError::Internal)?;
+
+    Ok(Json(PurgeResponse {
+        channel_id,
+        purged,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/webhooks",
+    tag = "admin",
+    request_body = WebhookRequest,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The webhook and its signing secret, which is not shown again", body = CreatedWebhook),
+        (status = 400, description = "Invalid URL", body = ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+)]
+async fn create_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Result<Json<WebhookRequest>, axum::extract::rejection::JsonRejection>,
+) -> Result<Json<CreatedWebhook>, ApiError> {
+    authorize_admin(&state, &headers)?;
+    let Json(payload) = payload.map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    Ok(Json(state.webhooks.create(&payload.url)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/webhooks",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = WebhooksResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+)]
+async fn webhooks_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<WebhooksResponse>, ApiError> {
+    authorize_admin(&state, &headers)?;
+
+    Ok(Json(WebhooksResponse {
+        items: state.webhooks.list()?,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/webhooks/{id}",
+    tag = "admin",
+    params(("id" = String, Path)),
+    security(("admin_token" = [])),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 404, description = "No such webhook", body = ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+)]
+async fn delete_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    authorize_admin(&state, &headers)?;
+
+    if !state.webhooks.remove(&id)? {
+        return Err(ApiError::NotFound("Webhook not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/webhooks/deliveries",
+    tag = "admin",
+    params(DeliveriesQuery),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, body = DeliveriesResponse),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+)]
+async fn deliveries_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<DeliveriesQuery>,
+) -> Result<Json<DeliveriesResponse>, ApiError> {
+    authorize_admin(&state, &headers)?;
+
+    Ok(Json(DeliveriesResponse {
+        items: state.webhooks.deliveries(query.status)?,
     }))
 }
 
@@ -288,14 +1161,1220 @@ async fn index_handler() -> Html<String> {
     Html(html_content.to_string())
 }
 
-pub fn create_router() -> Router {
-    let client = reqwest::Client::new();
-    let state = Arc::new(AppState { client });
+/// A channel that is not going anywhere, looked up by the readiness canary
+const CANARY_CHANNEL_ID: &str = "UCBR8-60-B28hp2BmDPdntcQ";
+const CANARY_URL: &str = "youtube.com/@youtube";
+
+/// How long a canary result is reused. Probes asking for it every few seconds
+/// still spend one quota unit at most this often.
+const CANARY_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is up", body = HealthResponse)),
+)]
+async fn healthz_handler() -> Json<HealthResponse> {
+    Json(HealthResponse { status: "ok" })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    params(ReadinessQuery),
+    responses(
+        (status = 200, description = "Ready to serve lookups", body = ReadinessResponse),
+        (status = 503, description = "No quota left", body = ReadinessResponse),
+    ),
+)]
+async fn readyz_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReadinessQuery>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let quota = state.lookup.quota();
+    let keys_with_quota = quota.iter().filter(|usage| usage.remaining > 0).count();
+    let canary = match query.canary {
+        true => Some(canary(&state).await),
+        false => None,
+    };
+
+    let mut problems = Vec::new();
+    if keys_with_quota == 0 {
+        problems.push("Every API key is out of quota until the daily reset".to_string());
+    }
 
-    Router::new()
-        .route("/", get(index_handler))  // Add this line for serving the HTML
-        .route("/api/videos", post(videos_handler))
-        .route("/api/subscriptions", post(subscriptions_handler))
-        .route("/api/channel", post(channel_handler))
-        .with_state(state)
-}
\ No newline at end of file
+    let ready = problems.is_empty();
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadinessResponse {
+        ready,
+        problems,
+        quota_remaining: quota.iter().map(|usage| usage.remaining).sum(),
+        keys_with_quota,
+        canary,
+    }))
+}
+
+/// The last canary report, or a new one if it is older than `CANARY_TTL`
+async fn canary(state: &AppState) -> CanaryReport {
+    let now = Utc::now().timestamp();
+    if let Some(report) = state.canary.lock().as_ref() {
+        if now - report.checked_at < CANARY_TTL.as_secs() as i64 {
+            return report.clone();
+        }
+    }
+
+    let report = run_canary(state, now).await;
+    *state.canary.lock() = Some(report.clone());
+    report
+}
+
+/// Runs every step of a handle lookup against the canary channel, bypassing
+/// the cache so that a broken parser can't hide behind a cached response
+async fn run_canary(state: &AppState, now: i64) -> CanaryReport {
+    fn check(result: Result<(), String>) -> CanaryCheck {
+        CanaryCheck { ok: result.is_ok(), error: result.err() }
+    }
+
+    let resolve_url = match state.lookup.resolve_url(CANARY_URL.to_string(), true).await {
+        Ok(cached) => match cached.value {
+            ResolveUrlResult::BrowseEndpoint { browse_id } if browse_id == CANARY_CHANNEL_ID => Ok(()),
+            other => Err(format!("Expected {}, got {:?}", CANARY_CHANNEL_ID, other)),
+        },
+        Err(e) => Err(e.to_string()),
+    };
+
+    let (channel, browse) = match state.lookup.channel(YTLookupType::ChannelID(CANARY_CHANNEL_ID.to_string()), true).await {
+        Ok(cached) => {
+            let browse = match state.lookup.enrichment(&cached.value, true).await {
+                Ok(enrichment) if enrichment.value.handle.is_some() => Ok(()),
+                Ok(_) => Err("No handle found in the browse response".to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            (Ok(()), browse)
+        }
+        Err(e) => (Err(e.to_string()), Err("Skipped because the channel lookup failed".to_string())),
+    };
+
+    let report = CanaryReport {
+        checked_at: now,
+        resolve_url: check(resolve_url),
+        channel: check(channel),
+        browse: check(browse),
+    };
+    if !(report.resolve_url.ok && report.channel.ok && report.browse.ok) {
+        warn!(?report, "Canary lookup failed");
+    }
+    report
+}
+
+/// Runs every request inside a `request` span, then logs and counts it by
+/// route and status once the handler returns. Streamed bodies are still
+/// being written at that point.
+async fn trace_request(matched_path: Option<MatchedPath>, request: Request, next: Next) -> Response {
+    let route = matched_path.as_ref().map_or("unmatched", MatchedPath::as_str);
+    let span = info_span!(
+        "request",
+        method = %request.method(),
+        route,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    );
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = route.to_string();
+    let response = next.run(request).instrument(span.clone()).await;
+    let status = response.status().as_u16();
+
+    counter!(telemetry::HTTP_REQUESTS, "route" => route.clone(), "method" => method.clone(), "status" => status.to_string()).increment(1);
+    histogram!(telemetry::HTTP_REQUEST_DURATION, "route" => route, "method" => method).record(started.elapsed());
+    span.record("status", status);
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+    info!(parent: &span, "Request finished");
+    response
+}
+
+/// Prometheus text format. Quota gauges are brought up to date first, since
+/// keys only report their usage when asked.
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    for (index, usage) in state.lookup.quota().into_iter().enumerate() {
+        let key = index.to_string();
+        gauge!(telemetry::QUOTA_USED, "key" => key.clone()).set(usage.used as f64);
+        gauge!(telemetry::QUOTA_REMAINING, "key" => key).set(usage.remaining as f64);
+    }
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], telemetry::prometheus().render())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "youtube-lookup",
+        description = "Looks up YouTube channels by any identifier, along with the fields only InnerTube has.",
+    ),
+    paths(
+        channel_handler,
+        get_channel_handler,
+        batch_handler,
+        history_handler,
+        videos_handler,
+        subscriptions_handler,
+        get_videos_handler,
+        get_subscriptions_handler,
+        export_channel_handler,
+        export_videos_handler,
+        export_subscriptions_handler,
+        watchlist_handler,
+        watch_handler,
+        unwatch_handler,
+        watch_events_handler,
+        purge_cache_handler,
+        create_webhook_handler,
+        webhooks_handler,
+        delete_webhook_handler,
+        deliveries_handler,
+        healthz_handler,
+        readyz_handler,
+    ),
+    components(schemas(CrawlResponse<Video>, CrawlResponse<Subscription>)),
+    modifiers(&AdminToken),
+    tags(
+        (name = "channels", description = "Channel lookups"),
+        (name = "crawls", description = "Videos and subscriptions, a page at a time or all at once"),
+        (name = "exports", description = "CSV and JSON Lines downloads"),
+        (name = "watchlist", description = "Channels re-checked in the background"),
+        (name = "admin", description = "Only mounted when an admin token is configured"),
+        (name = "health", description = "Liveness and readiness probes, always mounted"),
+    ),
+)]
+struct ApiDoc;
+
+/// Admin routes take the token as `Authorization: Bearer ...`
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+async fn openapi_handler() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn docs_handler() -> Html<&'static str> {
+    Html(include_str!("../../static/docs.html"))
+}
+
+pub fn create_router(config: Config) -> Result<Router, String> {
+    let (youtube, youtubei) = lookup::clients(&config)?;
+    // Installed before the first upstream call so nothing goes unrecorded
+    telemetry::prometheus();
+
+    // Without a data directory everything is kept in memory, and the response
+    // cache does not need a second in-memory copy
+    let mut cache = ResponseCache::new(config.cache);
+    let store = match &config.data_dir {
+        Some(data_dir) => {
+            let store = Arc::new(Store::open(data_dir)?);
+            cache = cache.with_store(store.clone())?;
+            store
+        }
+        None => Arc::new(Store::in_memory()?),
+    };
+    let history = ChannelHistory::new(store.clone(), config.limits.max_snapshots)
+        .map_err(|e| format!("Could not open channel history: {}", e))?;
+
+    let lookup = Arc::new(Lookup::new(youtube, youtubei, cache));
+    let webhooks = Arc::new(
+        Webhooks::new(store.clone(), config.retry.webhook.clone())
+            .map_err(|e| format!("Could not open the webhook outbox: {}", e))?,
+    );
+    let watchlist = Arc::new(
+        Watchlist::new(store, lookup.clone(), webhooks.clone(), config.watch_interval)
+            .map_err(|e| format!("Could not open the watchlist: {}", e))?,
+    );
+    // Re-checks and deliveries stop once the router is dropped
+    watchlist.spawn();
+    webhooks.spawn();
+
+    let state = Arc::new(AppState {
+        lookup,
+        history,
+        watchlist,
+        webhooks,
+        admin_token: config.admin_token,
+        limits: config.limits,
+        canary: Mutex::new(None),
+    });
+
+    let enabled = |group| config.routes.contains(&group);
+    let mut router = Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler));
+
+    if enabled(RouteGroup::Ui) {
+        router = router.route("/", get(index_handler));
+    }
+    if enabled(RouteGroup::Docs) {
+        router = router
+            .route("/api/openapi.json", get(openapi_handler))
+            .route("/api/docs", get(docs_handler));
+    }
+    if enabled(RouteGroup::Channels) {
+        router = router
+            .route("/api/channel", post(channel_handler))
+            .route("/api/channel/history", post(history_handler))
+            .route("/api/channels/batch", post(batch_handler))
+            .route("/api/channels/:type/:id", get(get_channel_handler));
+    }
+    if enabled(RouteGroup::Crawls) {
+        router = router
+            .route("/api/videos", post(videos_handler))
+            .route("/api/subscriptions", post(subscriptions_handler))
+            .route("/api/channels/:id/videos", get(get_videos_handler))
+            .route("/api/channels/:id/subscriptions", get(get_subscriptions_handler));
+    }
+    if enabled(RouteGroup::Exports) {
+        router = router
+            .route("/api/export/channel", get(export_channel_handler))
+            .route("/api/export/videos", get(export_videos_handler))
+            .route("/api/export/subscriptions", get(export_subscriptions_handler));
+    }
+    if enabled(RouteGroup::Watchlist) {
+        router = router
+            .route("/api/watchlist", get(watchlist_handler).post(watch_handler))
+            .route("/api/watchlist/events", get(watch_events_handler))
+            .route("/api/watchlist/:channel_id", delete(unwatch_handler));
+    }
+    if enabled(RouteGroup::Admin) && state.admin_token.is_some() {
+        router = router
+            .route("/api/admin/cache/:channel_id", delete(purge_cache_handler))
+            .route("/api/admin/webhooks", get(webhooks_handler).post(create_webhook_handler))
+            .route("/api/admin/webhooks/deliveries", get(deliveries_handler))
+            .route("/api/admin/webhooks/:id", delete(delete_webhook_handler));
+    }
+
+    if enabled(RouteGroup::Metrics) {
+        router = router.route("/metrics", get(metrics_handler));
+    }
+
+    Ok(router
+        .layer(middleware::from_fn(trace_request))
+        .with_state(state))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use crate::test_support::{browse_endpoint, fixture, not_found, url_endpoint, MockYouTube};
+    use crate::config::Limits;
+
+    async fn serve(mock: &MockYouTube) -> String {
+        serve_config(mock.config()).await
+    }
+
+    async fn serve_config(config: Config) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = create_router(config).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    /// Sends one request straight to `app` without binding a port
+    async fn call(app: Router, method: &str, uri: &str, body: Option<&Value>, admin_token: Option<&str>) -> (u16, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = admin_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+
+        let resp = app.oneshot(request.unwrap()).await.unwrap();
+        let status = resp.status().as_u16();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    async fn lookup(mock: &MockYouTube, body: Value) -> (u16, Value) {
+        let base_url = serve(mock).await;
+        let resp = reqwest::Client::new()
+            .post(format!("{}/api/channel", base_url))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_channel_id_lookup() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, fixture("innertube/browse_verified.json")).await;
+        mock.resolve_url("youtube.com/@youtube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(body["channel"]["verification"], "verified");
+        assert_eq!(body["channel"]["no_index"], false);
+        assert_eq!(body["redirect_url"], Value::Null);
+        assert_eq!(body.get("interpretation"), None);
+    }
+
+    #[tokio::test]
+    async fn test_channel_id_lookup_terminated() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCterminated")], 200, fixture("data_api/channel_empty.json")).await;
+        mock.data_api("subscriptions", &[("channelId", "UCterminated")], 403, fixture("data_api/error_account_suspended.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "CHANNEL_ID", "id": "UCterminated" })).await;
+
+        assert_eq!(status, 404);
+        assert_eq!(body["message"], "This channel has been terminated");
+    }
+
+    #[tokio::test]
+    async fn test_channel_id_lookup_closed() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCclosed")], 200, fixture("data_api/channel_empty.json")).await;
+        mock.data_api("subscriptions", &[("channelId", "UCclosed")], 403, fixture("data_api/error_account_closed.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "CHANNEL_ID", "id": "UCclosed" })).await;
+
+        assert_eq!(status, 404);
+        assert_eq!(body["message"], "This channel has been deleted");
+    }
+
+    #[tokio::test]
+    async fn test_handle_lookup_with_redirect() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("forHandle", "TeamYouTube")], 200, fixture("data_api/channel_team_youtube.json")).await;
+        mock.browse("UCK8sQmJBp8GCxrOtXWBpyEA", 200, fixture("innertube/browse_blocked_us.json")).await;
+        mock.resolve_url("youtube.com/@teamyoutube", 200, url_endpoint("https://support.google.com/youtube")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "HANDLE", "id": "TeamYouTube" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCK8sQmJBp8GCxrOtXWBpyEA");
+        assert_eq!(body["channel"]["blocked_countries"].as_array().map(|c| c.len()), Some(2));
+        assert_eq!(body["redirect_url"], "https://support.google.com/youtube");
+    }
+
+    #[tokio::test]
+    async fn test_vanity_lookup_survives_enrichment_failure() {
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/YOUTUBE", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        mock.resolve_url("youtube.com/+YouTube", 404, not_found()).await;
+        mock.resolve_url("youtube.com/user/YouTube", 200, browse_endpoint("UCsomeoneelse")).await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 500, "{}".to_string()).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "VANITY", "id": "YouTube" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(body["channel"]["verification"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_vanity_lookup_rejects_user_url() {
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/YOUTUBE", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        mock.resolve_url("youtube.com/+YouTube", 404, not_found()).await;
+        mock.resolve_url("youtube.com/user/YouTube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "VANITY", "id": "YouTube" })).await;
+
+        assert_eq!(status, 404);
+        assert_eq!(body["message"], "Not a vanity URL");
+    }
+
+    #[tokio::test]
+    async fn test_custom_url_lookup() {
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/+NikPMusic", 200, browse_endpoint("UCK8sQmJBp8GCxrOtXWBpyEA")).await;
+        mock.resolve_url("youtube.com/NIKPMUSIC", 200, fixture("innertube/resolve_url.json")).await;
+        mock.data_api("channels", &[("id", "UCK8sQmJBp8GCxrOtXWBpyEA")], 200, fixture("data_api/channel_team_youtube.json")).await;
+        mock.browse("UCK8sQmJBp8GCxrOtXWBpyEA", 200, fixture("innertube/browse_oac.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "CUSTOM_URL", "id": "NikPMusic" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["verification"], "oac");
+        assert_eq!(body["redirect_url"], "http://www.youtube.com/channel/UCtI6KR_Y7memgBmEW0p8POw");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_quota_exceeded() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 403, fixture("data_api/error_quota.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "USERNAME", "id": "YouTube" })).await;
+
+        assert_eq!(status, 429);
+        assert_eq!(body["error"], "rate_limited");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_invalid_request() {
+        let mock = MockYouTube::start().await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "NOT_A_TYPE", "id": "YouTube" })).await;
+
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn test_auto_lookup_handle_url() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("forHandle", "TeamYouTube")], 200, fixture("data_api/channel_team_youtube.json")).await;
+        mock.browse("UCK8sQmJBp8GCxrOtXWBpyEA", 200, fixture("innertube/browse_blocked_us.json")).await;
+        mock.resolve_url("youtube.com/@teamyoutube", 200, browse_endpoint("UCK8sQmJBp8GCxrOtXWBpyEA")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "AUTO", "id": "https://m.youtube.com/@TeamYouTube/videos" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCK8sQmJBp8GCxrOtXWBpyEA");
+        assert_eq!(body["interpretation"], json!({ "type": "HANDLE", "id": "TeamYouTube", "matched": "@handle URL" }));
+    }
+
+    #[tokio::test]
+    async fn test_auto_lookup_video_url() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        mock.data_api("videos", &[("id", "dQw4w9WgXcQ")], 200, fixture("data_api/video_snippet.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "AUTO", "id": "https://youtu.be/dQw4w9WgXcQ?t=42" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(body["interpretation"]["type"], "VIDEO");
+        assert_eq!(body["interpretation"]["id"], "dQw4w9WgXcQ");
+        assert_eq!(body["video"]["title"], "Rick Astley - Never Gonna Give You Up (Official Music Video)");
+    }
+
+    #[tokio::test]
+    async fn test_playlist_id_lookup() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        mock.data_api("playlists", &[("id", "PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb")], 200, fixture("data_api/playlist_snippet.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "PLAYLIST_ID", "id": "PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb" })).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(body["channel"]["verification"], "verified");
+        assert_eq!(body["playlist"]["title"], "YouTube Originals");
+        assert_eq!(body["playlist"]["video_count"], 42);
+        assert_eq!(body.get("video"), None);
+    }
+
+    #[tokio::test]
+    async fn test_video_id_lookup_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("videos", &[("id", "deleted0000")], 200, fixture("data_api/channel_empty.json")).await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "VIDEO_ID", "id": "deleted0000" })).await;
+
+        assert_eq!(status, 404);
+        assert_eq!(body["message"], "Video not found");
+    }
+
+    #[tokio::test]
+    async fn test_auto_lookup_unrecognized_input() {
+        let mock = MockYouTube::start().await;
+
+        let (status, body) = lookup(&mock, json!({ "type": "AUTO", "id": "https://example.com/@youtube" })).await;
+
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "Not a recognized YouTube URL or identifier");
+    }
+
+    async fn youtube_mocks(mock: &MockYouTube) {
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, fixture("innertube/browse_verified.json")).await;
+        mock.resolve_url("youtube.com/@youtube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+    }
+
+    #[tokio::test]
+    async fn test_repeated_lookup_is_served_from_cache() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let base_url = serve(&mock).await;
+        let client = reqwest::Client::new();
+        let body = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+
+        let first = client.post(format!("{}/api/channel", base_url)).json(&body).send().await.unwrap();
+        assert_eq!(first.headers()["x-cache"], "MISS");
+        assert!(first.headers().get("age").is_none());
+
+        let second = client.post(format!("{}/api/channel", base_url)).json(&body).send().await.unwrap();
+        assert_eq!(second.status(), 200);
+        assert_eq!(second.headers()["x-cache"], "HIT");
+        assert!(second.headers().get("age").is_some());
+        let second: Value = second.json().await.unwrap();
+        assert_eq!(second["channel"]["verification"], "verified");
+
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+        assert_eq!(mock.request_count("/youtubei/v1/browse").await, 1);
+        assert_eq!(mock.request_count("/youtubei/v1/navigation/resolve_url").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_cache_forces_refetch() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let base_url = serve(&mock).await;
+        let client = reqwest::Client::new();
+        let body = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+
+        client.post(format!("{}/api/channel", base_url)).json(&body).send().await.unwrap();
+        let refreshed = client
+            .post(format!("{}/api/channel", base_url))
+            .header("Cache-Control", "no-cache")
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(refreshed.headers()["x-cache"], "MISS");
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_terminated_channel_is_cached() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCterminated")], 200, fixture("data_api/channel_empty.json")).await;
+        mock.data_api("subscriptions", &[("channelId", "UCterminated")], 403, fixture("data_api/error_account_suspended.json")).await;
+        let base_url = serve(&mock).await;
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let resp = client
+                .post(format!("{}/api/channel", base_url))
+                .json(&json!({ "type": "CHANNEL_ID", "id": "UCterminated" }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 404);
+            let body: Value = resp.json().await.unwrap();
+            assert_eq!(body["message"], "This channel has been terminated");
+        }
+
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+        assert_eq!(mock.request_count("/youtube/v3/subscriptions").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_survives_restart_until_purged() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let data_dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            data_dir: Some(data_dir.path().to_path_buf()),
+            admin_token: Some("admin-token".to_string()),
+            ..mock.config()
+        };
+        let body = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+
+        // Each router opens the store, so only one may be alive at a time
+        {
+            let app = create_router(config.clone()).unwrap();
+            let resp = call(app, "POST", "/api/channel", Some(&body), None).await;
+            assert_eq!(resp.0, 200);
+        }
+
+        let app = create_router(config.clone()).unwrap();
+        let resp = call(app.clone(), "POST", "/api/channel", Some(&body), None).await;
+        assert_eq!(resp.0, 200);
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+
+        for token in ["wrong-token", "admin", "admin-token-2"] {
+            let (status, _) = call(app.clone(), "DELETE", "/api/admin/cache/UCBR8-60-B28hp2BmDPdntcQ", None, Some(token)).await;
+            assert_eq!(status, 401);
+        }
+
+        let (status, purged) = call(app.clone(), "DELETE", "/api/admin/cache/UCBR8-60-B28hp2BmDPdntcQ", None, Some("admin-token")).await;
+        assert_eq!(status, 200);
+        assert_eq!(purged["purged"], 3);
+
+        let (status, _) = call(app, "POST", "/api/channel", Some(&body), None).await;
+        assert_eq!(status, 200);
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_groups_channel_ids() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        mock.resolve_url("youtube.com/@TeamYouTube", 200, browse_endpoint("UCK8sQmJBp8GCxrOtXWBpyEA")).await;
+        mock.resolve_url("youtube.com/@teamyoutube", 200, browse_endpoint("UCK8sQmJBp8GCxrOtXWBpyEA")).await;
+        mock.browse("UCK8sQmJBp8GCxrOtXWBpyEA", 200, fixture("innertube/browse_blocked_us.json")).await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ,UCK8sQmJBp8GCxrOtXWBpyEA,UCterminated")], 200, fixture("data_api/channels_batch.json")).await;
+        mock.data_api("subscriptions", &[("channelId", "UCterminated")], 403, fixture("data_api/error_account_suspended.json")).await;
+
+        let items = json!({ "items": [
+            { "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" },
+            { "type": "HANDLE", "id": "@TeamYouTube" },
+            { "type": "CHANNEL_ID", "id": "UCterminated" },
+            { "type": "AUTO", "id": "https://example.com/@youtube" },
+        ]});
+        let (status, body) = call(create_router(mock.config()).unwrap(), "POST", "/api/channels/batch", Some(&items), None).await;
+
+        assert_eq!(status, 200);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0]["id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(results[0]["channel"]["verification"], "verified");
+        assert_eq!(results[1]["channel"]["user_id"], "UCK8sQmJBp8GCxrOtXWBpyEA");
+        assert_eq!(results[1]["channel"]["blocked_countries"].as_array().map(|c| c.len()), Some(2));
+        assert_eq!(results[2]["error"], json!({ "status": 404, "error": "not_found", "message": "This channel has been terminated" }));
+        assert_eq!(results[3]["error"]["status"], 400);
+
+        // One Data API call for the channels, one to work out why the missing one is gone
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+        assert_eq!(mock.request_count("/youtube/v3/subscriptions").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_survives_failed_channels_call() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("forHandle", "TeamYouTube")], 200, fixture("data_api/channel_team_youtube.json")).await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 403, fixture("data_api/error_quota.json")).await;
+        mock.resolve_url("youtube.com/@TeamYouTube", 404, not_found()).await;
+        mock.resolve_url("youtube.com/@teamyoutube", 200, browse_endpoint("UCK8sQmJBp8GCxrOtXWBpyEA")).await;
+
+        let items = json!({ "items": [
+            { "type": "HANDLE", "id": "TeamYouTube" },
+            { "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" },
+        ]});
+        let (status, body) = call(create_router(mock.config()).unwrap(), "POST", "/api/channels/batch", Some(&items), None).await;
+
+        assert_eq!(status, 200);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results[0]["channel"]["user_id"], "UCK8sQmJBp8GCxrOtXWBpyEA");
+        assert_eq!(results[1]["id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(results[1]["error"]["error"], "rate_limited");
+
+        // The handle's channel came from its own lookup rather than a second fetch
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_rejects_oversized_batches() {
+        let mock = MockYouTube::start().await;
+        let app = create_router(mock.config()).unwrap();
+
+        let items: Vec<Value> = (0..=Limits::default().max_batch_size).map(|i| json!({ "type": "CHANNEL_ID", "id": format!("UC{}", i) })).collect();
+        let (status, body) = call(app.clone(), "POST", "/api/channels/batch", Some(&json!({ "items": items })), None).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "invalid_request");
+
+        let (status, _) = call(app, "POST", "/api/channels/batch", Some(&json!({ "items": [] })), None).await;
+        assert_eq!(status, 400);
+    }
+
+    /// Sends `body` with `Accept: application/x-ndjson` and parses each line
+    async fn ndjson_lines(base_url: &str, route: &str, body: Value) -> Vec<Value> {
+        let resp = reqwest::Client::new()
+            .post(format!("{}{}", base_url, route))
+            .header(ACCEPT, NDJSON)
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[CONTENT_TYPE], NDJSON);
+
+        resp.text()
+            .await
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_videos_stream_every_page() {
+        let mock = MockYouTube::start().await;
+        let last_page = json!({ "items": [{ "snippet": { "publishedAt": "2023-07-01T12:00:00Z", "title": "Older", "resourceId": { "videoId": "olderVideo1" } } }] });
+        mock.data_api("playlistItems", &[("pageToken", "EAAaBlBUOkNBVQ")], 200, last_page.to_string()).await;
+        mock.data_api("playlistItems", &[("playlistId", "UUBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let base_url = serve(&mock).await;
+
+        let lines = ndjson_lines(&base_url, "/api/videos", json!({ "id": "UUBR8-60-B28hp2BmDPdntcQ" })).await;
+
+        assert_eq!(mock.request_count("/youtube/v3/playlistItems").await, 2);
+        assert_eq!(lines[0]["video_id"], "gfKpRpwHckY");
+        assert_eq!(lines[0]["views"], 1520);
+        assert_eq!(lines.last().unwrap()["video_id"], "olderVideo1");
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_stream_ends_with_error_line() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/subscriptions", &[
+            (200, fixture("data_api/subscriptions.json")),
+            (403, fixture("data_api/error_quota.json")),
+        ]).await;
+        let base_url = serve(&mock).await;
+
+        let lines = ndjson_lines(&base_url, "/api/subscriptions", json!({ "id": "UCBR8-60-B28hp2BmDPdntcQ" })).await;
+
+        let (error, subscriptions) = lines.split_last().unwrap();
+        assert!(!subscriptions.is_empty());
+        assert!(subscriptions.iter().all(|line| line["channel_id"].is_string()));
+        assert_eq!(error["error"]["error"], "rate_limited");
+        assert_eq!(error["page_token"], "CAUQAA");
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_crawl_returns_partial_results() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/subscriptions", &[
+            (200, fixture("data_api/subscriptions.json")),
+            (403, fixture("data_api/error_quota.json")),
+        ]).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let body = json!({ "id": "UCBR8-60-B28hp2BmDPdntcQ", "all": true });
+        let (status, body) = call(app, "POST", "/api/subscriptions", Some(&body), None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["items"].as_array().map(|items| items.len()), Some(2));
+        assert_eq!(body["complete"], false);
+        assert_eq!(body["page_token"], "CAUQAA");
+        assert_eq!(body["error"]["error"], "rate_limited");
+    }
+
+    #[tokio::test]
+    async fn test_videos_crawl_stops_at_max_items() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let body = json!({ "id": "UUBR8-60-B28hp2BmDPdntcQ", "all": true, "max_items": 3 });
+        let (status, body) = call(app, "POST", "/api/videos", Some(&body), None).await;
+
+        // Every page points to another one, so only the cap ends the crawl
+        assert_eq!(status, 200);
+        assert_eq!(body["items"].as_array().map(|items| items.len()), Some(3));
+        assert_eq!(body["complete"], false);
+        assert_eq!(body["error"], Value::Null);
+        assert_eq!(mock.request_count("/youtube/v3/playlistItems").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_stopping_at_the_end_of_a_page_fetches_no_more() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let app = create_router(mock.config()).unwrap();
+
+        // The first page has two videos and a next page token
+        let body = json!({ "id": "UUBR8-60-B28hp2BmDPdntcQ", "all": true, "max_items": 2 });
+        let (status, body) = call(app, "POST", "/api/videos", Some(&body), None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["items"].as_array().map(|items| items.len()), Some(2));
+        assert_eq!(body["complete"], false);
+        assert_eq!(mock.request_count("/youtube/v3/playlistItems").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_max_items_is_capped() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let mut config = mock.config();
+        config.limits.max_crawl_items = 3;
+        let app = create_router(config).unwrap();
+
+        let body = json!({ "id": "UUBR8-60-B28hp2BmDPdntcQ", "all": true, "max_items": 18446744073709551615u64 });
+        let (status, body) = call(app, "POST", "/api/videos", Some(&body), None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["items"].as_array().map(|items| items.len()), Some(3));
+        assert_eq!(body["complete"], false);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_with_zero_max_items() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let body = json!({ "id": "UUBR8-60-B28hp2BmDPdntcQ", "all": true, "max_items": 0 });
+        let (status, body) = call(app, "POST", "/api/videos", Some(&body), None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["items"], json!([]));
+        assert_eq!(body["complete"], false);
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_streams_results() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        let base_url = serve(&mock).await;
+
+        let items = json!({ "items": [
+            { "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" },
+            { "type": "AUTO", "id": "not a channel" },
+        ]});
+        let lines = ndjson_lines(&base_url, "/api/channels/batch", items).await;
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(lines[1]["id"], "not a channel");
+        assert_eq!(lines[1]["error"]["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_revalidates_with_etag() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let base_url = serve(&mock).await;
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/channels/channel-id/UCBR8-60-B28hp2BmDPdntcQ", base_url);
+
+        let first = client.get(&url).send().await.unwrap();
+        assert_eq!(first.status(), 200);
+        assert!(first.headers().contains_key(LAST_MODIFIED));
+        let etag = first.headers()[ETAG].clone();
+        let body: Value = first.json().await.unwrap();
+        assert_eq!(body["channel"]["user_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+
+        let second = client.get(&url).header(IF_NONE_MATCH, etag.clone()).send().await.unwrap();
+        assert_eq!(second.status(), 304);
+        assert_eq!(second.headers()[ETAG], etag);
+        assert_eq!(second.headers()["x-cache"], "HIT");
+        assert!(second.bytes().await.unwrap().is_empty());
+
+        let changed = client.get(&url).header(IF_NONE_MATCH, "\"stale\"").send().await.unwrap();
+        assert_eq!(changed.status(), 200);
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_videos_uses_uploads_playlist() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[("playlistId", "UUBR8-60-B28hp2BmDPdntcQ"), ("pageToken", "EAAaBlBUOkNBVQ")], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let uri = "/api/channels/UCBR8-60-B28hp2BmDPdntcQ/videos?page_token=EAAaBlBUOkNBVQ";
+        let (status, body) = call(app, "GET", uri, None, None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["items"][0]["video_id"], "gfKpRpwHckY");
+        assert_eq!(body["page_token"], "EAAaBlBUOkNBVQ");
+    }
+
+    #[tokio::test]
+    async fn test_get_routes_reject_bad_paths() {
+        let mock = MockYouTube::start().await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, _) = call(app.clone(), "GET", "/api/channels/nonsense/youtube", None, None).await;
+        assert_eq!(status, 400);
+
+        let (status, body) = call(app, "GET", "/api/channels/UUBR8-60-B28hp2BmDPdntcQ/videos", None, None).await;
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "Expected a channel ID starting with UC");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_route_groups_are_not_served() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let config = Config {
+            routes: vec![RouteGroup::Channels],
+            admin_token: Some("admin-token".to_string()),
+            ..mock.config()
+        };
+        let app = create_router(config).unwrap();
+
+        let body = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+        let (status, _) = call(app.clone(), "POST", "/api/channel", Some(&body), None).await;
+        assert_eq!(status, 200);
+
+        for (method, uri) in [("GET", "/api/openapi.json"), ("GET", "/api/watchlist"), ("GET", "/api/admin/webhooks")] {
+            let response = app.clone()
+                .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 404, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let base_url = serve(&mock).await;
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let resp = client.post(format!("{}/api/channel", base_url))
+                .json(&json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+        }
+        let resp = reqwest::get(format!("{}/metrics", base_url)).await.unwrap();
+
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "text/plain; version=0.0.4");
+        // The recorder is shared by every test in the process, so only the
+        // presence of each series can be checked
+        let text = resp.text().await.unwrap();
+        for series in [
+            r#"http_requests_total{route="/api/channel",method="POST",status="200"}"#,
+            r#"http_request_duration_seconds_bucket{route="/api/channel",method="POST",le="0.005"}"#,
+            r#"youtube_upstream_requests_total{api="data_api",endpoint="channels",outcome="ok"}"#,
+            r#"youtube_upstream_requests_total{api="innertube",endpoint="browse",outcome="ok"}"#,
+            r#"youtube_cache_lookups_total{resource="channel",result="miss"}"#,
+            r#"youtube_cache_lookups_total{resource="channel",result="hit"}"#,
+            r#"youtube_quota_used_units{key="0"}"#,
+            r#"youtube_quota_remaining_units{key="0"}"#,
+            "# HELP youtube_quota_remaining_units",
+        ] {
+            assert!(text.contains(series), "{} missing from\n{}", series, text);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readiness_follows_quota() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let app = create_router(Config { daily_quota: 1, ..mock.config() }).unwrap();
+
+        let (status, body) = call(app.clone(), "GET", "/healthz", None, None).await;
+        assert_eq!((status, body), (200, json!({ "status": "ok" })));
+
+        let (status, body) = call(app.clone(), "GET", "/readyz", None, None).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, json!({ "ready": true, "problems": [], "quota_remaining": 1, "keys_with_quota": 1 }));
+
+        // A channel lookup spends the only unit
+        let lookup = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+        let (status, _) = call(app.clone(), "POST", "/api/channel", Some(&lookup), None).await;
+        assert_eq!(status, 200);
+
+        let (status, body) = call(app, "GET", "/readyz", None, None).await;
+        assert_eq!(status, 503);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["problems"], json!(["Every API key is out of quota until the daily reset"]));
+        assert_eq!(body["keys_with_quota"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_canary() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, body) = call(app.clone(), "GET", "/readyz?canary=true", None, None).await;
+
+        assert_eq!(status, 200);
+        let canary = &body["canary"];
+        for check in ["resolve_url", "channel", "browse"] {
+            assert_eq!(canary[check], json!({ "ok": true, "error": null }), "{}", check);
+        }
+
+        // Probes within the TTL reuse the report instead of spending quota
+        let calls = mock.request_count("/youtube/v3/channels").await;
+        let (_, again) = call(app, "GET", "/readyz?canary=true", None, None).await;
+        assert_eq!(again["canary"], body["canary"]);
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, calls);
+    }
+
+    #[tokio::test]
+    async fn test_failing_canary_is_reported_without_failing_readiness() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        mock.resolve_url("youtube.com/@youtube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, "{}".to_string()).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, body) = call(app, "GET", "/readyz?canary=true", None, None).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(body["canary"]["resolve_url"]["ok"], true);
+        assert_eq!(body["canary"]["channel"]["ok"], true);
+        assert_eq!(body["canary"]["browse"], json!({ "ok": false, "error": "No handle found in the browse response" }));
+    }
+
+    #[tokio::test]
+    async fn test_openapi_document() {
+        let mock = MockYouTube::start().await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, spec) = call(app, "GET", "/api/openapi.json", None, None).await;
+
+        assert_eq!(status, 200);
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        for path in ["/api/channel", "/api/channels/{type}/{id}", "/api/channels/batch", "/api/export/videos", "/api/admin/webhooks"] {
+            assert!(spec["paths"][path].is_object(), "{}", path);
+        }
+        let schemas = &spec["components"]["schemas"];
+        assert!(schemas["Channel"]["properties"]["user_id"].is_object());
+        assert_eq!(schemas["ChannelLookupRequest"]["properties"]["type"]["$ref"], "#/components/schemas/LookupType");
+        assert_eq!(schemas["ErrorBody"]["required"], json!(["error", "message"]));
+        assert_eq!(spec["paths"]["/api/admin/webhooks"]["get"]["security"], json!([{ "admin_token": [] }]));
+    }
+
+    #[tokio::test]
+    async fn test_channel_export_csv() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let base_url = serve(&mock).await;
+
+        let resp = reqwest::get(format!("{}/api/export/channel?type=CHANNEL_ID&id=UCBR8-60-B28hp2BmDPdntcQ", base_url)).await.unwrap();
+
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(resp.headers()[CONTENT_DISPOSITION], "attachment; filename=\"channel-UCBR8-60-B28hp2BmDPdntcQ.csv\"");
+        let csv = resp.text().await.unwrap();
+        let mut lines = csv.trim_start_matches('\u{feff}').lines();
+        assert_eq!(lines.next().unwrap(), Channel::COLUMNS.join(","));
+        assert!(lines.next().unwrap().starts_with("UCBR8-60-B28hp2BmDPdntcQ,YouTube,"));
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_export_reports_partial_crawl() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/subscriptions", &[
+            (200, fixture("data_api/subscriptions.json")),
+            (403, fixture("data_api/error_quota.json")),
+        ]).await;
+        let base_url = serve(&mock).await;
+
+        let resp = reqwest::get(format!("{}/api/export/subscriptions?id=UCBR8-60-B28hp2BmDPdntcQ&format=jsonl", base_url)).await.unwrap();
+
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[CONTENT_DISPOSITION], "attachment; filename=\"subscriptions-UCBR8-60-B28hp2BmDPdntcQ.jsonl\"");
+        assert_eq!(resp.headers()["x-export-complete"], "false");
+        assert_eq!(resp.headers()["x-export-page-token"], "CAUQAA");
+        assert_eq!(resp.headers()["x-export-error"], "rate_limited");
+        let rows: Vec<Value> = resp.text().await.unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row["created_at_rfc3339"].is_string()));
+    }
+
+    #[tokio::test]
+    async fn test_videos_export_max_items_is_capped() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 200, fixture("data_api/playlist_items.json")).await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+        let mut config = mock.config();
+        config.limits.max_crawl_items = 3;
+        let base_url = serve_config(config).await;
+
+        let resp = reqwest::get(format!("{}/api/export/videos?id=UUBR8-60-B28hp2BmDPdntcQ&format=jsonl&max_items=18446744073709551615", base_url)).await.unwrap();
+
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["x-export-complete"], "false");
+        assert_eq!(resp.text().await.unwrap().lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_unknown_format() {
+        let mock = MockYouTube::start().await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, body) = call(app, "GET", "/api/export/videos?id=PL123&format=xlsx", None, None).await;
+
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "invalid_request");
+        assert_eq!(mock.request_count("/youtube/v3/playlistItems").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_history_tracks_changes_between_lookups() {
+        let mock = MockYouTube::start().await;
+        let before = fixture("data_api/channel_youtube.json");
+        let after = before
+            .replace("\"customUrl\": \"@youtube\"", "\"customUrl\": \"@youtubeofficial\"")
+            .replace("\"subscriberCount\": \"20300000\"", "\"subscriberCount\": \"20301000\"");
+        mock.sequence("/youtube/v3/channels", &[(200, before), (200, after)]).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 500, "{}".to_string()).await;
+        mock.resolve_url("youtube.com/@youtube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        mock.resolve_url("youtube.com/@youtubeofficial", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        let app = create_router(mock.config()).unwrap();
+        let lookup = json!({ "type": "CHANNEL_ID", "id": "UCBR8-60-B28hp2BmDPdntcQ" });
+
+        let (status, _) = call(app.clone(), "POST", "/api/channel/history", Some(&json!({ "id": "UCBR8-60-B28hp2BmDPdntcQ" })), None).await;
+        assert_eq!(status, 404);
+
+        for _ in 0..2 {
+            // Skip the cache so the second lookup sees the renamed channel
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/channel")
+                .header("content-type", "application/json")
+                .header("cache-control", "no-cache")
+                .body(Body::from(lookup.to_string()))
+                .unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), 200);
+        }
+
+        let (status, body) = call(app, "POST", "/api/channel/history", Some(&json!({ "id": "UCBR8-60-B28hp2BmDPdntcQ" })), None).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["snapshots"].as_array().map(|s| s.len()), Some(2));
+        assert_eq!(body["diffs"][0]["subscriber_delta"], 1000);
+        assert_eq!(body["diffs"][0]["changes"][0]["field"], "handle");
+        assert_eq!(body["diffs"][0]["changes"][0]["old"], "youtube");
+        assert_eq!(body["diffs"][0]["changes"][0]["new"], "youtubeofficial");
+    }
+
+    #[tokio::test]
+    async fn test_watchlist_routes() {
+        let mock = MockYouTube::start().await;
+        youtube_mocks(&mock).await;
+        let app = create_router(mock.config()).unwrap();
+
+        let (status, watched) = call(app.clone(), "POST", "/api/watchlist", Some(&json!({ "id": "UCBR8-60-B28hp2BmDPdntcQ" })), None).await;
+        assert_eq!(status, 200);
+        assert_eq!(watched["status"]["status"], "active");
+        assert_eq!(watched["status"]["handle"], "youtube");
+
+        let (_, list) = call(app.clone(), "GET", "/api/watchlist", None, None).await;
+        assert_eq!(list["items"][0]["channel_id"], "UCBR8-60-B28hp2BmDPdntcQ");
+
+        let (status, events) = call(app.clone(), "GET", "/api/watchlist/events?channel_id=UCBR8-60-B28hp2BmDPdntcQ", None, None).await;
+        assert_eq!(status, 200);
+        assert_eq!(events["items"], json!([]));
+
+        let (status, _) = call(app.clone(), "DELETE", "/api/watchlist/UCBR8-60-B28hp2BmDPdntcQ", None, None).await;
+        assert_eq!(status, 204);
+        let (status, _) = call(app, "DELETE", "/api/watchlist/UCBR8-60-B28hp2BmDPdntcQ", None, None).await;
+        assert_eq!(status, 404);
+    }
+}
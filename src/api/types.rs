@@ -1,12 +1,28 @@
+use std::sync::Arc;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
-use crate::models::{Video, Subscription, Channel};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::{Video, Subscription, Channel, PlaylistSummary, VideoSummary};
+use crate::history::{ChannelHistory, Snapshot, SnapshotDiff};
+use crate::config::Limits;
+use crate::detect::Detection;
+use crate::export::Format;
+use crate::lookup::Lookup;
+use crate::watchlist::{WatchEvent, WatchedChannel, Watchlist};
+use crate::webhooks::{Delivery, DeliveryStatus, Webhook, Webhooks};
 
 pub struct AppState {
-    pub client: Client,
+    pub lookup: Arc<Lookup>,
+    pub history: ChannelHistory,
+    pub watchlist: Arc<Watchlist>,
+    pub webhooks: Arc<Webhooks>,
+    pub admin_token: Option<String>,
+    pub limits: Limits,
+    /// Last canary run, reused by readiness probes for a while
+    pub canary: Mutex<Option<CanaryReport>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LookupType {
     CustomUrl,
@@ -14,34 +30,248 @@ pub enum LookupType {
     Username,
     Handle,
     ChannelId,
+    VideoId,
+    PlaylistId,
+    /// Any channel, video or playlist URL, handle or channel ID
+    Auto,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChannelLookupRequest {
     pub r#type: LookupType,
     pub id: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChannelLookupResponse {
     pub channel: Channel,
     pub redirect_url: Option<String>,
+    /// The video a `VIDEO_ID` lookup started from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video: Option<VideoSummary>,
+    /// The playlist a `PLAYLIST_ID` lookup started from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playlist: Option<PlaylistSummary>,
+    /// How an `AUTO` lookup was interpreted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpretation: Option<Detection>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    pub items: Vec<ChannelLookupRequest>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    /// One per input, in the same order
+    pub results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResult {
+    /// `id` of the input this result is for
+    pub id: String,
+    #[serde(flatten)]
+    pub outcome: BatchOutcome,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchOutcome {
+    Found(Box<ChannelLookupResponse>),
+    Failed { error: ErrorDetails },
+}
+
+/// The body of an error response plus its status, for errors reported inside
+/// a successful response
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ErrorDetails {
+    pub status: u16,
+    pub error: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct PaginatedRequest {
     pub id: String,
     pub page_token: Option<String>,
+    /// Crawl every page from `page_token` on instead of returning one
+    #[serde(default)]
+    pub all: bool,
+    /// Stop a crawl after this many items
+    pub max_items: Option<usize>,
+}
+
+/// Everything a crawl found, for requests with `all` set
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CrawlResponse<T> {
+    pub items: Vec<T>,
+    /// False if the crawl stopped before the last page, either at
+    /// `max_items` or because an upstream call failed
+    pub complete: bool,
+    /// Page to resume from after a failure
+    pub page_token: Option<String>,
+    pub error: Option<ErrorDetails>,
+}
+
+/// One line of a streamed crawl
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum CrawlLine<T> {
+    Item(T),
+    Failed {
+        error: ErrorDetails,
+        page_token: Option<String>,
+    },
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    pub page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChannelExportQuery {
+    pub r#type: LookupType,
+    pub id: String,
+    #[serde(default)]
+    pub format: Format,
+}
+
+/// Exports crawl every page from `page_token` on, like requests with `all` set
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CrawlExportQuery {
+    pub id: String,
+    pub page_token: Option<String>,
+    pub max_items: Option<usize>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PlaylistItemsResponse {
     pub items: Vec<Video>,
     pub page_token: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SubscriptionsResponse {
     pub items: Vec<Subscription>,
     pub page_token: Option<String>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct HistoryRequest {
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistoryResponse {
+    pub channel_id: String,
+    /// Oldest first
+    pub snapshots: Vec<Snapshot>,
+    /// One entry per pair of consecutive snapshots
+    pub diffs: Vec<SnapshotDiff>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WatchRequest {
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WatchlistResponse {
+    pub items: Vec<WatchedChannel>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WatchEventsQuery {
+    pub channel_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WatchEventsResponse {
+    /// Newest first
+    pub items: Vec<WatchEvent>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PurgeResponse {
+    pub channel_id: String,
+    /// Number of cache entries removed
+    pub purged: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WebhookRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhooksResponse {
+    pub items: Vec<Webhook>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveriesQuery {
+    pub status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeliveriesResponse {
+    pub items: Vec<Delivery>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    pub status: &'static str,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReadinessQuery {
+    /// Also look up a known channel through InnerTube and the Data API
+    #[serde(default)]
+    pub canary: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// False if every key is out of quota
+    pub ready: bool,
+    /// Why the service is not ready. The configuration is not checked here,
+    /// since the service refuses to start with an invalid one.
+    pub problems: Vec<String>,
+    /// Estimated Data API quota units left today, across every key
+    pub quota_remaining: u64,
+    pub keys_with_quota: usize,
+    /// Only with `canary=true`. Failures are reported but don't affect `ready`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryReport>,
+}
+
+/// Lookups of a known channel that show whether each upstream call still
+/// works and still parses
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CanaryReport {
+    pub checked_at: i64,
+    /// `resolve_url` of the channel's handle, InnerTube
+    pub resolve_url: CanaryCheck,
+    /// The channel by ID, Data API
+    pub channel: CanaryCheck,
+    /// The channel's browse page, InnerTube
+    pub browse: CanaryCheck,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CanaryCheck {
+    pub ok: bool,
+    pub error: Option<String>,
+}
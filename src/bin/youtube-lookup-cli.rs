@@ -0,0 +1,410 @@
+//! Channel lookups from a terminal. Talks to the Data API and InnerTube
+//! directly, with the same configuration as the server but no cache.
+
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use chrono::{DateTime, SecondsFormat};
+use clap::{Parser, Subcommand};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use youtube_lookup::config::{Args, Config, LogConfig};
+use youtube_lookup::detect::{detect, Target};
+use youtube_lookup::errors::YouTubeError;
+use youtube_lookup::logging;
+use youtube_lookup::lookup::clients;
+use youtube_lookup::models::{Channel, Subscription, Video};
+use youtube_lookup::youtube::channels::{get_channel, LookupType};
+use youtube_lookup::youtube::client::YouTubeDataClient;
+use youtube_lookup::youtube::pages::{CrawlError, Crawled};
+use youtube_lookup::youtube::playlist_items::stream_playlist_items;
+use youtube_lookup::youtube::playlists::get_playlist;
+use youtube_lookup::youtube::subscriptions::stream_subscriptions;
+use youtube_lookup::youtube::videos::get_video;
+use youtube_lookup::youtubei::browse::enrich_channel_data;
+use youtube_lookup::youtubei::client::YouTubeiClient;
+use youtube_lookup::youtubei::resolve_url::{resolve_url, ResolveUrlResult};
+
+/// Items asked of the Data API per page
+const MAX_RESULTS: u32 = 50;
+
+/// Titles and descriptions are cut to this many characters in tables
+const MAX_CELL_WIDTH: usize = 60;
+
+#[derive(Parser)]
+#[command(name = "youtube-lookup-cli", version, about = "Looks up YouTube channels from the terminal")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+    /// The server's settings, of which the lookups use the API keys,
+    /// upstream URLs, timeouts and logging
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Look up a channel by URL, handle, channel ID, video or playlist
+    Lookup { input: String },
+    /// List a channel's uploads, newest first
+    Videos {
+        channel: String,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// List the channels a channel subscribes to
+    Subs {
+        channel: String,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Look up every line of a file, or of stdin with `-`
+    Batch {
+        file: PathBuf,
+        /// Lookups made at the same time
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+    },
+}
+
+struct Clients {
+    youtube: YouTubeDataClient,
+    youtubei: YouTubeiClient,
+}
+
+impl Clients {
+    fn new(config: &Config) -> Result<Self, String> {
+        let (youtube, youtubei) = clients(config)?;
+        Ok(Self { youtube, youtubei })
+    }
+
+    fn target(input: &str) -> Result<Target, String> {
+        detect(input)
+            .map(|detection| detection.target)
+            .ok_or_else(|| format!("Not a recognized YouTube URL or identifier: {}", input))
+    }
+
+    /// The channel ID behind a URL or identifier. Names go through
+    /// `resolve_url`, videos and playlists through their owner.
+    async fn channel_id(&self, input: &str) -> Result<String, String> {
+        let url = match Self::target(input)? {
+            Target::ChannelId(id) => return Ok(id),
+            Target::Handle(handle) => {
+                return get_channel(&self.youtube, LookupType::Handle(handle)).await.map(|c| c.user_id).map_err(describe)
+            }
+            Target::Username(name) => {
+                return get_channel(&self.youtube, LookupType::Username(name)).await.map(|c| c.user_id).map_err(describe)
+            }
+            Target::Video(id) => return get_video(&self.youtube, &id).await.map(|v| v.channel_id).map_err(describe),
+            Target::Playlist(id) => return get_playlist(&self.youtube, &id).await.map(|p| p.channel_id).map_err(describe),
+            Target::CustomUrl(name) => format!("youtube.com/+{}", name),
+            Target::Vanity(name) => format!("youtube.com/{}", name),
+            Target::Url(url) => url,
+        };
+
+        match resolve_url(&self.youtubei, url).await.map_err(describe)? {
+            ResolveUrlResult::BrowseEndpoint { browse_id } => Ok(browse_id),
+            ResolveUrlResult::UrlEndpoint { .. } => Err("Channel not found".to_string()),
+        }
+    }
+
+    /// The channel with its InnerTube-only fields, when InnerTube cooperates
+    async fn channel(&self, input: &str) -> Result<Channel, String> {
+        let lookup_type = match Self::target(input)? {
+            Target::Handle(handle) => LookupType::Handle(handle),
+            Target::Username(name) => LookupType::Username(name),
+            _ => LookupType::ChannelID(self.channel_id(input).await?),
+        };
+        let mut channel = get_channel(&self.youtube, lookup_type).await.map_err(describe)?;
+
+        if let Err(e) = enrich_channel_data(&self.youtubei, &mut channel).await {
+            eprintln!("Could not fetch InnerTube fields of {}: {}", channel.user_id, e);
+        }
+        Ok(channel)
+    }
+}
+
+fn describe(err: YouTubeError) -> String {
+    match err {
+        YouTubeError::NotFound => "Channel not found".to_string(),
+        YouTubeError::Ratelimited => "Rate limited, every API key is out of quota".to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// First line of `text`, cut to `MAX_CELL_WIDTH` characters
+fn cell(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() <= MAX_CELL_WIDTH {
+        return line.to_string();
+    }
+    let mut cut: String = line.chars().take(MAX_CELL_WIDTH - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Left-aligned columns separated by two spaces, one line per row
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let format_row = |values: &mut dyn Iterator<Item = &str>| {
+        let line: Vec<String> = values
+            .zip(&widths)
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect();
+        format!("{}\n", line.join("  ").trim_end())
+    };
+    let mut table = format_row(&mut headers.iter().copied());
+    for row in rows {
+        table.push_str(&format_row(&mut row.iter().map(String::as_str)));
+    }
+    table
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    print!("{}", table(headers, rows));
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).expect("output is always serializable"));
+}
+
+fn print_channel(channel: &Channel) {
+    let fields = [
+        ("Channel ID", channel.user_id.clone()),
+        ("Name", optional(channel.display_name.as_ref())),
+        ("Handle", optional(channel.handle.as_ref())),
+        ("Created", date(channel.created_at)),
+        ("Country", optional(channel.country.as_ref())),
+        ("Subscribers", channel.subscriber_count.to_string()),
+        ("Views", channel.view_count.to_string()),
+        ("Videos", channel.video_count.to_string()),
+        ("Made for kids", channel.made_for_kids.to_string()),
+        ("Verification", optional(channel.verification.as_ref().map(|status| format!("{:?}", status)))),
+        ("No index", optional(channel.no_index)),
+        ("Redirect", optional(channel.conditional_redirect.as_ref())),
+        ("Blocked in", optional(channel.blocked_countries.as_ref().map(|countries| countries.join(", ")))),
+        ("Description", cell(channel.description.as_deref().unwrap_or_default())),
+    ];
+    let rows: Vec<Vec<String>> = fields.into_iter().map(|(name, value)| vec![name.to_string(), value]).collect();
+    print_table(&["Field", "Value"], &rows);
+}
+
+/// Up to `limit` items of a crawl. What came before a failure is still
+/// printed, followed by the failure.
+async fn collect<T>(items: impl Stream<Item = Result<Crawled<T>, CrawlError>>, limit: usize) -> (Vec<T>, Option<String>) {
+    let mut items = std::pin::pin!(items.take(limit));
+    let mut collected = Vec::new();
+    while let Some(item) = items.next().await {
+        match item {
+            Ok(crawled) => collected.push(crawled.item),
+            Err(e) => return (collected, Some(describe(e.error))),
+        }
+    }
+    (collected, None)
+}
+
+#[derive(Serialize)]
+struct BatchLine {
+    input: String,
+    #[serde(flatten)]
+    outcome: BatchOutcome,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum BatchOutcome {
+    Channel(Box<Channel>),
+    Error(String),
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let filter_set = cli.args.log_filter.is_some();
+    let mut config = Config::load(cli.args).map_err(|e| e.to_string())?;
+    // Only retries and upstream surprises unless something asks for more
+    if !filter_set && config.log.filter == LogConfig::default().filter {
+        config.log.filter = "warn".to_string();
+    }
+    let _log_guard = logging::init(&config.log)?;
+    let clients = Clients::new(&config)?;
+
+    match cli.command {
+        Command::Lookup { input } => {
+            let channel = clients.channel(&input).await?;
+            match cli.json {
+                true => print_json(&channel),
+                false => print_channel(&channel),
+            }
+        }
+        Command::Videos { channel, limit } => {
+            let channel_id = clients.channel_id(&channel).await?;
+            let uploads = match channel_id.strip_prefix("UC") {
+                Some(id) => format!("UU{}", id),
+                None => return Err(format!("Not a channel ID: {}", channel_id)),
+            };
+            let (videos, error) = collect(stream_playlist_items(clients.youtube.clone(), uploads, None, MAX_RESULTS), limit).await;
+
+            if cli.json {
+                print_json(&videos);
+            } else {
+                let rows: Vec<Vec<String>> = videos
+                    .iter()
+                    .map(|video: &Video| vec![
+                        video.video_id.clone(),
+                        date(video.created_at),
+                        optional(video.views),
+                        optional(video.likes),
+                        optional(video.comments),
+                        cell(&video.title),
+                    ])
+                    .collect();
+                print_table(&["Video ID", "Published", "Views", "Likes", "Comments", "Title"], &rows);
+            }
+            if let Some(error) = error {
+                return Err(format!("Stopped after {} videos: {}", videos.len(), error));
+            }
+        }
+        Command::Subs { channel, limit } => {
+            let channel_id = clients.channel_id(&channel).await?;
+            let (subscriptions, error) = collect(stream_subscriptions(clients.youtube.clone(), channel_id, None, MAX_RESULTS), limit).await;
+
+            if cli.json {
+                print_json(&subscriptions);
+            } else {
+                let rows: Vec<Vec<String>> = subscriptions
+                    .iter()
+                    .map(|subscription: &Subscription| vec![
+                        subscription.channel_id.clone(),
+                        date(subscription.created_at),
+                        cell(&subscription.title),
+                    ])
+                    .collect();
+                print_table(&["Channel ID", "Subscribed", "Title"], &rows);
+            }
+            if let Some(error) = error {
+                return Err(format!("Stopped after {} subscriptions: {}", subscriptions.len(), error));
+            }
+        }
+        Command::Batch { file, concurrency } => {
+            let inputs: Vec<String> = if file.as_os_str() == "-" {
+                io::stdin().lock().lines().collect::<Result<_, _>>()
+            } else {
+                std::fs::read_to_string(&file).map(|text| text.lines().map(str::to_string).collect())
+            }
+            .map_err(|e| format!("Could not read {}: {}", file.display(), e))?;
+
+            let lines: Vec<BatchLine> = stream::iter(inputs.into_iter().map(|input| input.trim().to_string()).filter(|input| !input.is_empty()))
+                .map(|input| {
+                    let clients = &clients;
+                    async move {
+                        let outcome = match clients.channel(&input).await {
+                            Ok(channel) => BatchOutcome::Channel(Box::new(channel)),
+                            Err(e) => BatchOutcome::Error(e),
+                        };
+                        BatchLine { input, outcome }
+                    }
+                })
+                .buffered(concurrency.max(1))
+                .collect()
+                .await;
+
+            if cli.json {
+                print_json(&lines);
+            } else {
+                let rows: Vec<Vec<String>> = lines
+                    .iter()
+                    .map(|line| match &line.outcome {
+                        BatchOutcome::Channel(channel) => vec![
+                            cell(&line.input),
+                            channel.user_id.clone(),
+                            optional(channel.handle.as_ref()),
+                            channel.subscriber_count.to_string(),
+                            channel.video_count.to_string(),
+                            cell(channel.display_name.as_deref().unwrap_or_default()),
+                        ],
+                        BatchOutcome::Error(e) => vec![cell(&line.input), format!("error: {}", e)],
+                    })
+                    .collect();
+                print_table(&["Input", "Channel ID", "Handle", "Subscribers", "Videos", "Name"], &rows);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell() {
+        assert_eq!(cell("Short title"), "Short title");
+        assert_eq!(cell("First line\nSecond line"), "First line");
+        assert_eq!(cell(""), "");
+
+        let long = "é".repeat(MAX_CELL_WIDTH + 1);
+        let cut = cell(&long);
+        assert_eq!(cut.chars().count(), MAX_CELL_WIDTH);
+        assert!(cut.ends_with('…'));
+        assert_eq!(cell(&"é".repeat(MAX_CELL_WIDTH)).chars().count(), MAX_CELL_WIDTH);
+    }
+
+    #[test]
+    fn test_table() {
+        let rows = vec![
+            vec!["UCBR8-60-B28hp2BmDPdntcQ".to_string(), "YouTube".to_string()],
+            vec!["UC1".to_string(), String::new()],
+        ];
+        assert_eq!(
+            table(&["Channel ID", "Name"], &rows),
+            "Channel ID                Name\n\
+             UCBR8-60-B28hp2BmDPdntcQ  YouTube\n\
+             UC1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect() {
+        let items = || stream::iter(vec![
+            Ok(Crawled { item: 1, last: false }),
+            Ok(Crawled { item: 2, last: false }),
+            Err(CrawlError { error: YouTubeError::Ratelimited, page_token: Some("CAIQAA".to_string()) }),
+            Ok(Crawled { item: 3, last: true }),
+        ]);
+
+        assert_eq!(collect(items(), 1).await, (vec![1], None));
+        let (collected, error) = collect(items(), 10).await;
+        assert_eq!(collected, vec![1, 2]);
+        assert_eq!(error.as_deref(), Some("Rate limited, every API key is out of quota"));
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use parking_lot::Mutex;
+use redb::{Durability, TableDefinition};
+use crate::store::{Store, StoreError};
+use super::{CacheKey, Entry};
+
+/// JSON encoded `CacheKey` to timestamps followed by the cached body
+const ENTRIES: TableDefinition<&str, &[u8]> = TableDefinition::new("cache_entries");
+
+/// Eviction shrinks the cache to this share of its limit so that it does not
+/// run again on the very next insert
+const EVICT_TO: f64 = 0.9;
+
+/// Response cache entries in the persistent store, bounded by total size.
+/// Writes block on the store, so async callers should run them with
+/// `spawn_blocking`.
+pub struct DiskCache {
+    store: Arc<Store>,
+    max_bytes: u64,
+    /// Locked for the whole of every write so that it matches the table
+    index: Mutex<Index>,
+}
+
+/// The entries in the table from oldest to newest, so eviction does not have
+/// to read them all back
+#[derive(Default)]
+struct Index {
+    /// `stored_at` in milliseconds and encoded key to the entry's size
+    by_age: BTreeMap<(u64, String), u64>,
+    /// Bytes used by keys and values
+    size: u64,
+}
+
+impl Index {
+    fn add(&mut self, stored_at: u64, key: String, size: u64) {
+        if let Some(previous) = self.by_age.insert((stored_at, key), size) {
+            self.size -= previous;
+        }
+        self.size += size;
+    }
+
+    fn remove(&mut self, stored_at: u64, key: String) {
+        if let Some(size) = self.by_age.remove(&(stored_at, key)) {
+            self.size -= size;
+        }
+    }
+}
+
+impl DiskCache {
+    /// Drops expired entries and indexes what is left
+    pub fn open(store: Arc<Store>, max_bytes: u64) -> Result<Self, StoreError> {
+        let now = millis(SystemTime::now());
+        let mut index = Index::default();
+
+        let txn = store.db().begin_write()?;
+        {
+            let mut table = txn.open_table(ENTRIES)?;
+            table.retain(|key, value| match timestamps(value) {
+                Some((stored_at, expires_at)) if expires_at > now => {
+                    index.add(stored_at, key.to_string(), entry_size(key, value));
+                    true
+                }
+                _ => false,
+            })?;
+        }
+        txn.commit()?;
+
+        Ok(Self {
+            store,
+            max_bytes,
+            index: Mutex::new(index),
+        })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Result<Option<Entry>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(ENTRIES)?;
+        let entry = table
+            .get(encode_key(key).as_str())?
+            .and_then(|value| decode(value.value()));
+
+        // Expired entries are left for eviction to clean up
+        Ok(entry.filter(|entry| entry.expires_at > SystemTime::now()))
+    }
+
+    pub fn put(&self, key: &CacheKey, entry: &Entry) -> Result<(), StoreError> {
+        let key = encode_key(key);
+        let value = encode(entry);
+        let mut index = self.index.lock();
+
+        let mut txn = self.store.db().begin_write()?;
+        // Losing the last few writes in a crash is fine for a cache
+        txn.set_durability(Durability::Eventual);
+        let replaced = {
+            let mut table = txn.open_table(ENTRIES)?;
+            let previous = table.insert(key.as_str(), value.as_slice())?;
+            previous.and_then(|previous| timestamps(previous.value()))
+        };
+        txn.commit()?;
+
+        if let Some((stored_at, _)) = replaced {
+            index.remove(stored_at, key.clone());
+        }
+        let size = entry_size(&key, &value);
+        index.add(millis(entry.stored_at), key, size);
+
+        if index.size > self.max_bytes {
+            self.evict(&mut index)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the entries `matches` selects and returns their keys
+    pub fn purge<F>(&self, matches: F) -> Result<Vec<CacheKey>, StoreError>
+    where
+        F: Fn(&CacheKey, &[u8]) -> bool,
+    {
+        let mut purged = Vec::new();
+        let mut index = self.index.lock();
+
+        let txn = self.store.db().begin_write()?;
+        {
+            let mut table = txn.open_table(ENTRIES)?;
+            table.retain(|key, value| {
+                let (Some(cache_key), Some(entry)) = (decode_key(key), decode(value)) else {
+                    return true;
+                };
+                if !matches(&cache_key, &entry.body) {
+                    return true;
+                }
+                purged.push((millis(entry.stored_at), key.to_string(), cache_key));
+                false
+            })?;
+        }
+        txn.commit()?;
+
+        Ok(purged
+            .into_iter()
+            .map(|(stored_at, key, cache_key)| {
+                index.remove(stored_at, key);
+                cache_key
+            })
+            .collect())
+    }
+
+    /// Drops the oldest entries until the cache is back under its limit.
+    /// Expired entries are usually among them, as every entry of a resource
+    /// lives for the same time.
+    fn evict(&self, index: &mut Index) -> Result<(), StoreError> {
+        let target = (self.max_bytes as f64 * EVICT_TO) as u64;
+
+        let mut size = index.size;
+        let oldest: Vec<(u64, String)> = index
+            .by_age
+            .iter()
+            .take_while(|(_, entry_size)| {
+                let over = size > target;
+                size = size.saturating_sub(**entry_size);
+                over
+            })
+            .map(|(age_key, _)| age_key.clone())
+            .collect();
+
+        let txn = self.store.db().begin_write()?;
+        {
+            let mut table = txn.open_table(ENTRIES)?;
+            for (_, key) in &oldest {
+                table.remove(key.as_str())?;
+            }
+        }
+        txn.commit()?;
+
+        for (stored_at, key) in oldest {
+            index.remove(stored_at, key);
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn size(&self) -> u64 {
+        self.index.lock().size
+    }
+}
+
+fn encode_key(key: &CacheKey) -> String {
+    serde_json::to_string(key).expect("cache keys are always serializable")
+}
+
+fn decode_key(key: &str) -> Option<CacheKey> {
+    serde_json::from_str(key).ok()
+}
+
+fn entry_size(key: &str, value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// `stored_at` and `expires_at` as big-endian milliseconds since the epoch,
+/// followed by the body
+fn encode(entry: &Entry) -> Vec<u8> {
+    let mut value = Vec::with_capacity(16 + entry.body.len());
+    value.extend_from_slice(&millis(entry.stored_at).to_be_bytes());
+    value.extend_from_slice(&millis(entry.expires_at).to_be_bytes());
+    value.extend_from_slice(&entry.body);
+    value
+}
+
+/// `stored_at` and `expires_at` in milliseconds, without copying the body
+fn timestamps(value: &[u8]) -> Option<(u64, u64)> {
+    let stored_at = u64::from_be_bytes(value.get(0..8)?.try_into().ok()?);
+    let expires_at = u64::from_be_bytes(value.get(8..16)?.try_into().ok()?);
+    Some((stored_at, expires_at))
+}
+
+fn decode(value: &[u8]) -> Option<Entry> {
+    let (stored_at, expires_at) = timestamps(value)?;
+    Some(Entry {
+        stored_at: UNIX_EPOCH + Duration::from_millis(stored_at),
+        expires_at: UNIX_EPOCH + Duration::from_millis(expires_at),
+        body: value[16..].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Resource;
+
+    fn entry(body: &str, ttl: Duration) -> Entry {
+        let now = SystemTime::now();
+        Entry {
+            stored_at: now,
+            expires_at: now + ttl,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_entries_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = CacheKey::new(Resource::Channel, "UCBR8-60-B28hp2BmDPdntcQ", None);
+
+        {
+            let store = Arc::new(Store::open(dir.path()).unwrap());
+            let disk = DiskCache::open(store, 1024 * 1024).unwrap();
+            disk.put(&key, &entry("\"YouTube\"", Duration::from_secs(60))).unwrap();
+            disk.put(
+                &CacheKey::new(Resource::Channel, "UCexpired", None),
+                &entry("\"gone\"", Duration::ZERO),
+            ).unwrap();
+        }
+
+        let store = Arc::new(Store::open(dir.path()).unwrap());
+        let disk = DiskCache::open(store, 1024 * 1024).unwrap();
+        assert_eq!(disk.get(&key).unwrap().unwrap().body, b"\"YouTube\"");
+        assert!(disk.get(&CacheKey::new(Resource::Channel, "UCexpired", None)).unwrap().is_none());
+        assert_eq!(disk.size(), entry_size(&encode_key(&key), &encode(&entry("\"YouTube\"", Duration::ZERO))));
+    }
+
+    #[test]
+    fn test_oldest_entries_are_evicted_over_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(Store::open(dir.path()).unwrap());
+        let disk = DiskCache::open(store, 400).unwrap();
+
+        let body = "x".repeat(100);
+        for id in ["a", "b", "c", "d"] {
+            disk.put(&CacheKey::new(Resource::Channel, id, None), &entry(&body, Duration::from_secs(60))).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        assert!(disk.size() <= 400);
+        assert!(disk.get(&CacheKey::new(Resource::Channel, "a", None)).unwrap().is_none());
+        assert!(disk.get(&CacheKey::new(Resource::Channel, "d", None)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_replaced_entries_are_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(Store::open(dir.path()).unwrap());
+        let disk = DiskCache::open(store, 1024 * 1024).unwrap();
+        let key = CacheKey::new(Resource::Channel, "UCBR8-60-B28hp2BmDPdntcQ", None);
+
+        disk.put(&key, &entry("\"YouTube\"", Duration::from_secs(60))).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        disk.put(&key, &entry("\"YouTube!\"", Duration::from_secs(60))).unwrap();
+
+        assert_eq!(disk.size(), entry_size(&encode_key(&key), &encode(&entry("\"YouTube!\"", Duration::ZERO))));
+        assert_eq!(disk.index.lock().by_age.len(), 1);
+    }
+
+    #[test]
+    fn test_purge_removes_matching_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(Store::open(dir.path()).unwrap());
+        let disk = DiskCache::open(store, 1024 * 1024).unwrap();
+
+        let channel = CacheKey::new(Resource::Channel, "UCBR8-60-B28hp2BmDPdntcQ", None);
+        let handle = CacheKey::new(Resource::Channel, "handle:youtube", None);
+        let url = CacheKey::new(Resource::ResolveUrl, "youtube.com/@youtube", None);
+        let other = CacheKey::new(Resource::Channel, "UCK8sQmJBp8GCxrOtXWBpyEA", None);
+        let subscriptions = CacheKey::new(Resource::Subscriptions, "UCK8sQmJBp8GCxrOtXWBpyEA", None);
+        disk.put(&channel, &entry("{}", Duration::from_secs(60))).unwrap();
+        disk.put(&handle, &entry("{\"Ok\":{\"user_id\":\"UCBR8-60-B28hp2BmDPdntcQ\"}}", Duration::from_secs(60))).unwrap();
+        disk.put(&url, &entry("{\"Ok\":{\"BrowseEndpoint\":{\"browse_id\":\"UCBR8-60-B28hp2BmDPdntcQ\"}}}", Duration::from_secs(60))).unwrap();
+        disk.put(&other, &entry("{}", Duration::from_secs(60))).unwrap();
+        // Another channel's subscriptions that list this one
+        disk.put(&subscriptions, &entry("{\"Ok\":[{\"channel_id\":\"UCBR8-60-B28hp2BmDPdntcQ\"}]}", Duration::from_secs(60))).unwrap();
+
+        let purged = disk.purge(|key, body| key.belongs_to_channel(body, "UCBR8-60-B28hp2BmDPdntcQ")).unwrap();
+
+        assert_eq!(purged.len(), 3);
+        assert!(disk.get(&channel).unwrap().is_none());
+        assert!(disk.get(&handle).unwrap().is_none());
+        assert!(disk.get(&url).unwrap().is_none());
+        assert!(disk.get(&other).unwrap().is_some());
+        assert!(disk.get(&subscriptions).unwrap().is_some());
+    }
+}
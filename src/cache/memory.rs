@@ -0,0 +1,82 @@
+use std::collections::{BTreeMap, HashMap};
+use super::{CacheKey, Entry};
+
+/// Response cache entries in memory, bounded by count. The least recently
+/// used entry makes room for a new one.
+pub struct MemoryCache {
+    max_entries: usize,
+    /// Each entry along with its last use
+    entries: HashMap<CacheKey, (Entry, u64)>,
+    /// Last use to key, least recent first
+    recency: BTreeMap<u64, CacheKey>,
+    /// Increases on every use
+    clock: u64,
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub fn contains_key(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the entry for `key` and marks it as just used
+    pub fn get(&mut self, key: &CacheKey) -> Option<&Entry> {
+        let used = self.tick();
+        let (entry, last_used) = self.entries.get_mut(key)?;
+        self.recency.remove(last_used);
+        self.recency.insert(used, key.clone());
+        *last_used = used;
+        Some(entry)
+    }
+
+    pub fn insert(&mut self, key: CacheKey, entry: Entry) {
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            if let Some((_, least_recent)) = self.recency.pop_first() {
+                self.entries.remove(&least_recent);
+            }
+        }
+
+        let used = self.tick();
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (entry, used)) {
+            self.recency.remove(&last_used);
+        }
+        self.recency.insert(used, key);
+    }
+
+    pub fn remove(&mut self, key: &CacheKey) {
+        if let Some((_, last_used)) = self.entries.remove(key) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    /// Keeps only the entries `keep` returns true for
+    pub fn retain(&mut self, mut keep: impl FnMut(&CacheKey, &Entry) -> bool) {
+        let recency = &mut self.recency;
+        self.entries.retain(|key, (entry, last_used)| {
+            let kept = keep(key, entry);
+            if !kept {
+                recency.remove(last_used);
+            }
+            kept
+        });
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
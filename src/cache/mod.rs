@@ -0,0 +1,575 @@
+mod disk;
+mod memory;
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use metrics::counter;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use crate::errors::YouTubeError;
+use crate::store::Store;
+use crate::telemetry;
+use crate::youtubei::resolve_url::ResolveUrlResult;
+use disk::DiskCache;
+use memory::MemoryCache;
+
+/// The kinds of upstream responses kept in the cache, each with its own TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resource {
+    Channel,
+    ResolveUrl,
+    PlaylistItems,
+    Subscriptions,
+    Browse,
+    Video,
+    Playlist,
+}
+
+impl Resource {
+    /// Name used as a metrics label, the same as in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Resource::Channel => "channel",
+            Resource::ResolveUrl => "resolve_url",
+            Resource::PlaylistItems => "playlist_items",
+            Resource::Subscriptions => "subscriptions",
+            Resource::Browse => "browse",
+            Resource::Video => "video",
+            Resource::Playlist => "playlist",
+        }
+    }
+}
+
+/// Counts a cache lookup as a `hit`, a `miss`, or a `bypass` when the cache
+/// is off or a refresh was asked for
+fn count_lookup(resource: Resource, result: &'static str) {
+    counter!(telemetry::CACHE_LOOKUPS, "resource" => resource.as_str(), "result" => result).increment(1);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub resource: Resource,
+    /// Normalized identifier, e.g. `handle:teamyoutube` or a channel ID
+    pub id: String,
+    pub page_token: Option<String>,
+}
+
+impl CacheKey {
+    pub fn new(resource: Resource, id: impl Into<String>, page_token: Option<&str>) -> Self {
+        Self {
+            resource,
+            id: id.into(),
+            page_token: page_token.map(str::to_string),
+        }
+    }
+
+    /// Whether this entry belongs to `channel_id`: keyed by the channel or its
+    /// uploads playlist, or a handle, username or URL that resolved to it.
+    /// Other entries that merely mention the channel, like someone else's
+    /// subscriptions, are left alone.
+    fn belongs_to_channel(&self, body: &[u8], channel_id: &str) -> bool {
+        if channel_id.is_empty() {
+            return false;
+        }
+        let uploads = channel_id.strip_prefix("UC").map(|rest| format!("UU{}", rest));
+        if self.id == channel_id || uploads.is_some_and(|uploads| self.id == uploads) {
+            return true;
+        }
+
+        match self.resource {
+            Resource::Channel => matches!(
+                serde_json::from_slice::<Result<ChannelOwner, NegativeResult>>(body),
+                Ok(Ok(owner)) if owner.user_id == channel_id
+            ),
+            Resource::ResolveUrl => matches!(
+                serde_json::from_slice::<Result<ResolveUrlResult, NegativeResult>>(body),
+                Ok(Ok(ResolveUrlResult::BrowseEndpoint { browse_id })) if browse_id == channel_id
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// The part of a cached channel that says which channel it is
+#[derive(Deserialize)]
+struct ChannelOwner {
+    user_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheTtls {
+    pub channel: Duration,
+    pub resolve_url: Duration,
+    pub playlist_items: Duration,
+    pub subscriptions: Duration,
+    pub browse: Duration,
+    pub video: Duration,
+    pub playlist: Duration,
+    /// TTL for cached failures such as not found or terminated
+    pub negative: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Entries kept in memory
+    pub max_entries: usize,
+    /// Bytes kept in the on-disk cache when a data directory is configured
+    pub max_disk_bytes: u64,
+    pub ttls: CacheTtls,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 10_000,
+            max_disk_bytes: 256 * 1024 * 1024,
+            ttls: CacheTtls {
+                channel: Duration::from_secs(60 * 60),
+                resolve_url: Duration::from_secs(6 * 60 * 60),
+                playlist_items: Duration::from_secs(15 * 60),
+                subscriptions: Duration::from_secs(60 * 60),
+                browse: Duration::from_secs(60 * 60),
+                video: Duration::from_secs(24 * 60 * 60),
+                playlist: Duration::from_secs(24 * 60 * 60),
+                negative: Duration::from_secs(10 * 60),
+            },
+        }
+    }
+}
+
+impl CacheTtls {
+    fn for_resource(&self, resource: Resource) -> Duration {
+        match resource {
+            Resource::Channel => self.channel,
+            Resource::ResolveUrl => self.resolve_url,
+            Resource::PlaylistItems => self.playlist_items,
+            Resource::Subscriptions => self.subscriptions,
+            Resource::Browse => self.browse,
+            Resource::Video => self.video,
+            Resource::Playlist => self.playlist,
+        }
+    }
+}
+
+/// Failures that describe the looked-up entity rather than the request, and
+/// are therefore as cacheable as a successful response.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum NegativeResult {
+    NotFound,
+    AccountClosed,
+    AccountTerminated,
+    SubscriptionsPrivate,
+}
+
+impl NegativeResult {
+    fn from_error(err: &YouTubeError) -> Option<Self> {
+        match err {
+            YouTubeError::NotFound => Some(NegativeResult::NotFound),
+            YouTubeError::AccountClosed => Some(NegativeResult::AccountClosed),
+            YouTubeError::AccountTerminated => Some(NegativeResult::AccountTerminated),
+            YouTubeError::SubscriptionsPrivate => Some(NegativeResult::SubscriptionsPrivate),
+            _ => None,
+        }
+    }
+
+    fn into_error(self) -> YouTubeError {
+        match self {
+            NegativeResult::NotFound => YouTubeError::NotFound,
+            NegativeResult::AccountClosed => YouTubeError::AccountClosed,
+            NegativeResult::AccountTerminated => YouTubeError::AccountTerminated,
+            NegativeResult::SubscriptionsPrivate => YouTubeError::SubscriptionsPrivate,
+        }
+    }
+}
+
+/// A value together with how long ago it was fetched from upstream.
+/// `age` is `None` when the value was fetched for this request.
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    pub value: T,
+    pub age: Option<Duration>,
+}
+
+impl<T> Cached<T> {
+    pub fn fresh(value: T) -> Self {
+        Self { value, age: None }
+    }
+}
+
+/// Tracks the oldest cached piece that went into a response.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheAge(Option<Duration>);
+
+impl CacheAge {
+    pub fn track<T>(&mut self, cached: Cached<T>) -> T {
+        if let Some(age) = cached.age {
+            self.0 = Some(self.0.map_or(age, |oldest| oldest.max(age)));
+        }
+        cached.value
+    }
+
+    /// Folds in the ages tracked for another part of the same response
+    pub fn merge(&mut self, other: CacheAge) {
+        self.track(Cached { value: (), age: other.0 });
+    }
+
+    /// `None` if everything was fetched from upstream for this response
+    pub fn oldest(&self) -> Option<Duration> {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    stored_at: SystemTime,
+    expires_at: SystemTime,
+    /// JSON encoded `Result<T, NegativeResult>`
+    body: Vec<u8>,
+}
+
+/// Cache of upstream responses, kept in memory and optionally backed by the
+/// on-disk store so it survives restarts.
+pub struct ResponseCache {
+    config: CacheConfig,
+    entries: Mutex<MemoryCache>,
+    disk: Option<Arc<DiskCache>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            entries: Mutex::new(MemoryCache::new(config.max_entries)),
+            config,
+            disk: None,
+        }
+    }
+
+    /// Also keeps entries in `store`, dropping whatever expired while the
+    /// service was down
+    pub fn with_store(mut self, store: Arc<Store>) -> Result<Self, String> {
+        let disk = DiskCache::open(store, self.config.max_disk_bytes)
+            .map_err(|e| format!("Could not open the response cache: {}", e))?;
+        self.disk = Some(Arc::new(disk));
+        Ok(self)
+    }
+
+    /// Returns the cached result for `key`, or runs `fetch` and caches its
+    /// outcome. `refresh` skips the lookup but still stores the new result.
+    pub async fn get_or_fetch<T, F, Fut>(&self, key: CacheKey, refresh: bool, fetch: F) -> Result<Cached<T>, YouTubeError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, YouTubeError>>,
+    {
+        if !self.config.enabled {
+            count_lookup(key.resource, "bypass");
+            return fetch().await.map(Cached::fresh);
+        }
+
+        if refresh {
+            count_lookup(key.resource, "bypass");
+        } else if let Some((result, age)) = self.get::<T>(&key) {
+            count_lookup(key.resource, "hit");
+            return result.map(|value| Cached { value, age: Some(age) });
+        } else {
+            count_lookup(key.resource, "miss");
+        }
+
+        match fetch().await {
+            Ok(value) => {
+                self.put(key, &Ok::<&T, NegativeResult>(&value)).await;
+                Ok(Cached::fresh(value))
+            }
+            Err(err) => {
+                if let Some(negative) = NegativeResult::from_error(&err) {
+                    self.put(key, &Err::<&T, NegativeResult>(negative)).await;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// `get_or_fetch` for many keys at once. `fetch` is called a single time
+    /// with the keys that were not cached and returns the values it found;
+    /// keys it has no value for are cached as not found. Results are in the
+    /// order of `keys`.
+    pub async fn get_or_fetch_many<T, F, Fut>(
+        &self,
+        keys: &[CacheKey],
+        refresh: bool,
+        fetch: F,
+    ) -> Result<Vec<Result<Cached<T>, YouTubeError>>, YouTubeError>
+    where
+        T: Clone + Serialize + DeserializeOwned,
+        F: FnOnce(Vec<CacheKey>) -> Fut,
+        Fut: Future<Output = Result<HashMap<CacheKey, T>, YouTubeError>>,
+    {
+        let use_cache = self.config.enabled && !refresh;
+        let mut results: Vec<Option<Result<Cached<T>, YouTubeError>>> = keys
+            .iter()
+            .map(|key| {
+                if !use_cache {
+                    count_lookup(key.resource, "bypass");
+                    return None;
+                }
+                let Some((result, age)) = self.get::<T>(key) else {
+                    count_lookup(key.resource, "miss");
+                    return None;
+                };
+                count_lookup(key.resource, "hit");
+                Some(result.map(|value| Cached { value, age: Some(age) }))
+            })
+            .collect();
+
+        let mut missing = Vec::new();
+        for (key, result) in keys.iter().zip(&results) {
+            if result.is_none() && !missing.contains(key) {
+                missing.push(key.clone());
+            }
+        }
+        if missing.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
+
+        let fetched = fetch(missing).await?;
+        for (key, result) in keys.iter().zip(results.iter_mut()) {
+            if result.is_some() {
+                continue;
+            }
+            *result = Some(match fetched.get(key) {
+                Some(value) => {
+                    if self.config.enabled {
+                        self.put(key.clone(), &Ok::<&T, NegativeResult>(value)).await;
+                    }
+                    Ok(Cached::fresh(value.clone()))
+                }
+                None => {
+                    if self.config.enabled {
+                        self.put(key.clone(), &Err::<&T, NegativeResult>(NegativeResult::NotFound)).await;
+                    }
+                    Err(YouTubeError::NotFound)
+                }
+            });
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Removes every entry for `channel_id` and returns how many were dropped
+    pub async fn purge_channel(&self, channel_id: &str) -> Result<usize, String> {
+        let mut purged = HashSet::new();
+        self.entries.lock().retain(|key, entry| {
+            let mentioned = key.belongs_to_channel(&entry.body, channel_id);
+            if mentioned {
+                purged.insert(key.clone());
+            }
+            !mentioned
+        });
+
+        if let Some(disk) = &self.disk {
+            let disk = Arc::clone(disk);
+            let channel_id = channel_id.to_string();
+            let keys = tokio::task::spawn_blocking(move || {
+                disk.purge(|key, body| key.belongs_to_channel(body, &channel_id))
+            })
+            .await
+            .map_err(|e| format!("Could not purge the response cache: {}", e))?
+            .map_err(|e| format!("Could not purge the response cache: {}", e))?;
+            purged.extend(keys);
+        }
+
+        Ok(purged.len())
+    }
+
+    fn get<T: DeserializeOwned>(&self, key: &CacheKey) -> Option<(Result<T, YouTubeError>, Duration)> {
+        let now = SystemTime::now();
+        let entry = self.get_entry(key, now)?;
+
+        let result: Result<T, NegativeResult> = serde_json::from_slice(&entry.body).ok()?;
+        let age = now.duration_since(entry.stored_at).unwrap_or_default();
+        Some((result.map_err(NegativeResult::into_error), age))
+    }
+
+    fn get_entry(&self, key: &CacheKey, now: SystemTime) -> Option<Entry> {
+        {
+            let mut entries = self.entries.lock();
+            match entries.get(key) {
+                Some(entry) if entry.expires_at > now => return Some(entry.clone()),
+                Some(_) => entries.remove(key),
+                None => {}
+            }
+        }
+
+        // Fall back to disk and keep what is found there in memory
+        let entry = match self.disk.as_ref()?.get(key) {
+            Ok(entry) => entry?,
+            Err(e) => {
+                error!(?key, error = %e, "Failed to read cache entry");
+                return None;
+            }
+        };
+        self.insert_memory(key.clone(), entry.clone());
+        Some(entry)
+    }
+
+    async fn put<T: Serialize>(&self, key: CacheKey, result: &Result<T, NegativeResult>) {
+        let Ok(body) = serde_json::to_vec(result) else {
+            return;
+        };
+        let ttl = match result {
+            Ok(_) => self.config.ttls.for_resource(key.resource),
+            Err(_) => self.config.ttls.negative,
+        };
+        let now = SystemTime::now();
+        let entry = Entry {
+            stored_at: now,
+            expires_at: now + ttl,
+            body,
+        };
+
+        self.insert_memory(key.clone(), entry.clone());
+
+        if let Some(disk) = &self.disk {
+            // Writes wait on the store, so keep them off the async workers
+            let disk = Arc::clone(disk);
+            let written = tokio::task::spawn_blocking(move || disk.put(&key, &entry).map_err(|e| (key, e))).await;
+            match written {
+                Ok(Ok(())) => {}
+                Ok(Err((key, e))) => error!(?key, error = %e, "Failed to write cache entry"),
+                Err(e) => error!(error = %e, "Cache write task failed"),
+            }
+        }
+    }
+
+    fn insert_memory(&self, key: CacheKey, entry: Entry) {
+        self.entries.lock().insert(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn cache(max_entries: usize) -> ResponseCache {
+        ResponseCache::new(CacheConfig { max_entries, ..CacheConfig::default() })
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_caches_values() {
+        let cache = cache(10);
+        let calls = AtomicUsize::new(0);
+        let key = CacheKey::new(Resource::Channel, "UCBR8-60-B28hp2BmDPdntcQ", None);
+
+        for _ in 0..2 {
+            let cached = cache.get_or_fetch(key.clone(), false, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok("YouTube".to_string())
+            }).await.unwrap();
+            assert_eq!(cached.value, "YouTube");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A refresh bypasses the cached value
+        let cached = cache.get_or_fetch(key, true, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok("YouTube".to_string())
+        }).await.unwrap();
+        assert!(cached.age.is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_many_only_fetches_misses() {
+        let cache = cache(10);
+        let key = |id: &str| CacheKey::new(Resource::Channel, id, None);
+        cache.get_or_fetch(key("a"), false, || async { Ok("cached a".to_string()) }).await.unwrap();
+
+        let keys = [key("a"), key("b"), key("c"), key("b")];
+        let results = cache.get_or_fetch_many(&keys, false, |missing| async move {
+            assert_eq!(missing, [key("b"), key("c")]);
+            Ok(HashMap::from([(key("b"), "fetched b".to_string())]))
+        }).await.unwrap();
+
+        let values: Vec<Option<String>> = results.into_iter().map(|r| r.ok().map(|c| c.value)).collect();
+        assert_eq!(values, [Some("cached a".to_string()), Some("fetched b".to_string()), None, Some("fetched b".to_string())]);
+
+        // `c` is now cached as not found, so nothing is left to fetch
+        let results = cache.get_or_fetch_many::<String, _, _>(&keys, false, |_| async { unreachable!() }).await.unwrap();
+        assert!(matches!(results[2], Err(YouTubeError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_negative_results_are_cached() {
+        let cache = cache(10);
+        let key = CacheKey::new(Resource::Subscriptions, "UCterminated", None);
+
+        let first = cache.get_or_fetch::<String, _, _>(key.clone(), false, || async { Err(YouTubeError::AccountTerminated) }).await;
+        assert!(matches!(first, Err(YouTubeError::AccountTerminated)));
+
+        let second = cache.get_or_fetch::<String, _, _>(key, false, || async { Ok("unreachable".to_string()) }).await;
+        assert!(matches!(second, Err(YouTubeError::AccountTerminated)));
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_not_cached() {
+        let cache = cache(10);
+        let key = CacheKey::new(Resource::Browse, "UCBR8-60-B28hp2BmDPdntcQ", None);
+
+        let first = cache.get_or_fetch::<String, _, _>(key.clone(), false, || async { Err(YouTubeError::InternalServerError) }).await;
+        assert!(first.is_err());
+
+        let second = cache.get_or_fetch(key, false, || async { Ok("ok".to_string()) }).await;
+        assert_eq!(second.unwrap().value, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_page_tokens_are_separate_entries() {
+        let cache = cache(10);
+        let first = CacheKey::new(Resource::PlaylistItems, "UU123", None);
+        let second = CacheKey::new(Resource::PlaylistItems, "UU123", Some("CAUQAA"));
+
+        cache.get_or_fetch(first.clone(), false, || async { Ok(1) }).await.unwrap();
+        cache.get_or_fetch(second.clone(), false, || async { Ok(2) }).await.unwrap();
+
+        assert_eq!(cache.get_or_fetch(first, false, || async { Ok(0) }).await.unwrap().value, 1);
+        assert_eq!(cache.get_or_fetch(second, false, || async { Ok(0) }).await.unwrap().value, 2);
+    }
+
+    #[tokio::test]
+    async fn test_oldest_entry_is_evicted_when_full() {
+        let cache = cache(2);
+        for id in ["a", "b", "c"] {
+            cache.get_or_fetch(CacheKey::new(Resource::Channel, id, None), false, || async { Ok(id.to_string()) }).await.unwrap();
+        }
+
+        let entries = cache.entries.lock();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains_key(&CacheKey::new(Resource::Channel, "a", None)));
+    }
+
+    #[tokio::test]
+    async fn test_recently_used_entries_outlive_older_ones() {
+        let cache = cache(2);
+        let key = |id: &str| CacheKey::new(Resource::Channel, id, None);
+        for id in ["a", "b"] {
+            cache.get_or_fetch(key(id), false, || async { Ok(id.to_string()) }).await.unwrap();
+        }
+
+        // Reading `a` makes `b` the least recently used
+        cache.get_or_fetch(key("a"), false, || async { Ok(String::new()) }).await.unwrap();
+        cache.get_or_fetch(key("c"), false, || async { Ok("c".to_string()) }).await.unwrap();
+
+        let entries = cache.entries.lock();
+        assert!(entries.contains_key(&key("a")));
+        assert!(!entries.contains_key(&key("b")));
+        assert!(entries.contains_key(&key("c")));
+    }
+}
@@ -0,0 +1,599 @@
+use std::env;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use clap::{Parser, ValueEnum};
+use reqwest::Url;
+use serde::Deserialize;
+use crate::cache::{CacheConfig, CacheTtls};
+use crate::retry::RetryConfig;
+use crate::transport::TransportMode;
+use crate::youtube::keys::DEFAULT_DAILY_QUOTA;
+use crate::youtubei::client::DEFAULT_CLIENT_VERSION;
+
+pub const DEFAULT_DATA_API_URL: &str = "https://youtube.googleapis.com/youtube/v3";
+pub const DEFAULT_INNERTUBE_URL: &str = "https://www.youtube.com/youtubei/v1";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+}
+
+/// Groups of routes that can be turned off, named like the OpenAPI tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteGroup {
+    /// The web frontend at `/`
+    Ui,
+    /// `/api/openapi.json` and `/api/docs`
+    Docs,
+    Channels,
+    Crawls,
+    Exports,
+    Watchlist,
+    /// Also needs an admin token
+    Admin,
+    /// Prometheus metrics at `/metrics`
+    Metrics,
+}
+
+impl RouteGroup {
+    pub const ALL: [RouteGroup; 8] = [
+        RouteGroup::Ui,
+        RouteGroup::Docs,
+        RouteGroup::Channels,
+        RouteGroup::Crawls,
+        RouteGroup::Exports,
+        RouteGroup::Watchlist,
+        RouteGroup::Admin,
+        RouteGroup::Metrics,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One human readable line per event
+    #[default]
+    Text,
+    /// One JSON object per event, with the fields of every enclosing span
+    Json,
+}
+
+/// How often a new log file is started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Which events are kept, as `RUST_LOG` style directives, e.g.
+    /// `info,youtube_lookup=debug`
+    pub filter: String,
+    pub format: LogFormat,
+    /// Logs also go to rolling files in this directory, in `format`
+    pub dir: Option<PathBuf>,
+    pub rotation: LogRotation,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "info".to_string(),
+            format: LogFormat::default(),
+            dir: None,
+            rotation: LogRotation::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Most inputs accepted by `/api/channels/batch`
+    pub max_batch_size: usize,
+    /// Batch inputs resolved and enriched at the same time
+    pub batch_concurrency: usize,
+    /// Most items a crawl returns. Requests can ask for fewer with `max_items`.
+    pub max_crawl_items: usize,
+    /// Snapshots kept per channel in its history, oldest dropped first
+    pub max_snapshots: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 100,
+            batch_concurrency: 8,
+            max_crawl_items: 10_000,
+            max_snapshots: 1000,
+        }
+    }
+}
+
+impl Limits {
+    /// Items a crawl may return when a request asks for `requested`
+    pub fn crawl_items(&self, requested: Option<usize>) -> usize {
+        requested.map_or(self.max_crawl_items, |n| n.min(self.max_crawl_items))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Address the server listens on
+    pub bind: SocketAddr,
+    /// YouTube Data API keys, used in order as each one runs out of quota
+    pub api_keys: Vec<String>,
+    /// Quota units each key may spend per Pacific-time day
+    pub daily_quota: u64,
+    /// Base URL of the YouTube Data API v3, without a trailing slash
+    pub data_api_url: String,
+    /// Base URL of the InnerTube API, without a trailing slash
+    pub innertube_url: String,
+    /// `clientVersion` of the WEB client sent with every InnerTube request
+    pub innertube_client_version: String,
+    /// Longest a single upstream request may take, body included
+    pub upstream_timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Whether upstream traffic goes to the network, is recorded, or is replayed
+    pub transport: TransportMode,
+    /// Backoff limits for transient upstream failures
+    pub retry: RetryConfig,
+    /// Response cache settings
+    pub cache: CacheConfig,
+    pub limits: Limits,
+    /// Route groups that are served
+    pub routes: Vec<RouteGroup>,
+    /// Where persistent data is kept. Without one nothing outlives a restart.
+    pub data_dir: Option<PathBuf>,
+    /// Bearer token for the `/api/admin` routes, which are disabled without one
+    pub admin_token: Option<String>,
+    /// How often each channel on the watchlist is re-checked
+    pub watch_interval: Duration,
+    pub log: LogConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            api_keys: Vec::new(),
+            daily_quota: DEFAULT_DAILY_QUOTA,
+            data_api_url: DEFAULT_DATA_API_URL.to_string(),
+            innertube_url: DEFAULT_INNERTUBE_URL.to_string(),
+            innertube_client_version: DEFAULT_CLIENT_VERSION.to_string(),
+            upstream_timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            transport: TransportMode::Live,
+            retry: RetryConfig::default(),
+            cache: CacheConfig::default(),
+            limits: Limits::default(),
+            routes: RouteGroup::ALL.to_vec(),
+            data_dir: None,
+            admin_token: None,
+            watch_interval: Duration::from_secs(6 * 60 * 60),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+/// Command line flags. Every flag can also be given as the environment
+/// variable next to it, and flags win over the environment.
+#[derive(Debug, Default, Parser)]
+#[command(version, about = "Looks up YouTube channels by any identifier")]
+pub struct Args {
+    /// TOML file read before the environment and flags
+    #[arg(long, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "BIND")]
+    pub bind: Option<SocketAddr>,
+    /// Comma separated. `API_KEY` works too.
+    #[arg(long, env = "API_KEYS", value_delimiter = ',')]
+    pub api_keys: Option<Vec<String>>,
+    /// Quota units per key per day
+    #[arg(long, env = "DAILY_QUOTA")]
+    pub daily_quota: Option<u64>,
+    #[arg(long, env = "DATA_API_URL")]
+    pub data_api_url: Option<String>,
+    #[arg(long, env = "INNERTUBE_URL")]
+    pub innertube_url: Option<String>,
+    #[arg(long, env = "INNERTUBE_CLIENT_VERSION")]
+    pub innertube_client_version: Option<String>,
+    /// Seconds
+    #[arg(long, env = "UPSTREAM_TIMEOUT")]
+    pub upstream_timeout: Option<u64>,
+    /// Seconds
+    #[arg(long, env = "CONNECT_TIMEOUT")]
+    pub connect_timeout: Option<u64>,
+    #[arg(long, env = "MAX_BATCH_SIZE")]
+    pub max_batch_size: Option<usize>,
+    #[arg(long, env = "BATCH_CONCURRENCY")]
+    pub batch_concurrency: Option<usize>,
+    #[arg(long, env = "MAX_CRAWL_ITEMS")]
+    pub max_crawl_items: Option<usize>,
+    #[arg(long, env = "MAX_SNAPSHOTS")]
+    pub max_snapshots: Option<usize>,
+    #[arg(long, env = "CACHE_ENABLED")]
+    pub cache_enabled: Option<bool>,
+    #[arg(long, env = "CACHE_MAX_ENTRIES")]
+    pub cache_max_entries: Option<usize>,
+    #[arg(long, env = "CACHE_MAX_BYTES")]
+    pub cache_max_bytes: Option<u64>,
+    /// Comma separated route groups to serve
+    #[arg(long, env = "ROUTES", value_delimiter = ',')]
+    pub routes: Option<Vec<RouteGroup>>,
+    /// `live`, `record` or `replay`
+    #[arg(long, env = "CASSETTE_MODE")]
+    pub cassette_mode: Option<String>,
+    // Paths are taken as strings so that an empty `DATA_DIR=` reads as unset
+    #[arg(long, env = "CASSETTE_DIR")]
+    pub cassette_dir: Option<String>,
+    #[arg(long, env = "DATA_DIR")]
+    pub data_dir: Option<String>,
+    #[arg(long, env = "ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+    /// Seconds between re-checks of a watched channel
+    #[arg(long, env = "WATCH_INTERVAL")]
+    pub watch_interval: Option<u64>,
+    /// Which events are logged, e.g. `info,youtube_lookup=debug`
+    #[arg(long, env = "RUST_LOG")]
+    pub log_filter: Option<String>,
+    #[arg(long, env = "LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    /// Directory for rolling log files
+    #[arg(long, env = "LOG_DIR")]
+    pub log_dir: Option<String>,
+    #[arg(long, env = "LOG_ROTATION")]
+    pub log_rotation: Option<LogRotation>,
+}
+
+/// The config file. Durations are in seconds.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<SocketAddr>,
+    api_keys: Option<Vec<String>>,
+    daily_quota: Option<u64>,
+    routes: Option<Vec<RouteGroup>>,
+    data_dir: Option<PathBuf>,
+    admin_token: Option<String>,
+    watch_interval: Option<u64>,
+    upstream: UpstreamFile,
+    limits: LimitsFile,
+    cache: CacheFile,
+    cassette: CassetteFile,
+    log: LogFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UpstreamFile {
+    data_api_url: Option<String>,
+    innertube_url: Option<String>,
+    innertube_client_version: Option<String>,
+    timeout: Option<u64>,
+    connect_timeout: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsFile {
+    max_batch_size: Option<usize>,
+    batch_concurrency: Option<usize>,
+    max_crawl_items: Option<usize>,
+    max_snapshots: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheFile {
+    enabled: Option<bool>,
+    max_entries: Option<usize>,
+    max_disk_bytes: Option<u64>,
+    ttl: TtlFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TtlFile {
+    channel: Option<u64>,
+    resolve_url: Option<u64>,
+    playlist_items: Option<u64>,
+    subscriptions: Option<u64>,
+    browse: Option<u64>,
+    video: Option<u64>,
+    playlist: Option<u64>,
+    negative: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CassetteFile {
+    mode: Option<String>,
+    dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LogFile {
+    filter: Option<String>,
+    format: Option<LogFormat>,
+    dir: Option<PathBuf>,
+    rotation: Option<LogRotation>,
+}
+
+impl FileConfig {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })
+    }
+}
+
+fn secs(secs: Option<u64>, default: Duration) -> Duration {
+    secs.map(Duration::from_secs).unwrap_or(default)
+}
+
+/// Empty strings count as unset, so `ADMIN_TOKEN=` disables the admin routes
+fn non_empty<T: AsRef<str>>(value: Option<T>) -> Option<T> {
+    value.filter(|value| !value.as_ref().is_empty())
+}
+
+impl Config {
+    /// Builds the configuration from the defaults, then the TOML file given
+    /// by `--config` or `CONFIG_FILE`, then environment variables, then
+    /// flags, and checks all of it. See `Args` for the names.
+    ///
+    /// `DATA_API_URL` and `INNERTUBE_URL` fall back to the public YouTube
+    /// endpoints. Pointing these at a local server lets the whole service run
+    /// against a mock.
+    ///
+    /// `CASSETTE_MODE=record` writes every upstream exchange to `CASSETTE_DIR`
+    /// (default `cassettes`), and `CASSETTE_MODE=replay` serves them back offline.
+    pub fn load(mut args: Args) -> Result<Self, ConfigError> {
+        let file = match &args.config {
+            Some(path) => FileConfig::read(path)?,
+            None => FileConfig::default(),
+        };
+        if args.api_keys.is_none() {
+            args.api_keys = env::var("API_KEY").ok().map(|key| vec![key]);
+        }
+        let defaults = Self::default();
+
+        let mut problems = Vec::new();
+
+        let cassette_mode = args.cassette_mode.or(file.cassette.mode).unwrap_or_default();
+        let cassette_dir = non_empty(args.cassette_dir)
+            .map(PathBuf::from)
+            .or(file.cassette.dir)
+            .unwrap_or_else(|| PathBuf::from("cassettes"));
+        let transport = TransportMode::parse(&cassette_mode, cassette_dir).unwrap_or_else(|e| {
+            problems.push(format!("cassette mode: {}", e));
+            TransportMode::Live
+        });
+
+        // Admin routes are on by default but only mounted with a token, so
+        // only asking for them explicitly makes a missing token an error
+        let routes = args.routes.or(file.routes);
+
+        let ttl = file.cache.ttl;
+        let default_ttls = defaults.cache.ttls;
+        let cache = CacheConfig {
+            enabled: args.cache_enabled.or(file.cache.enabled).unwrap_or(defaults.cache.enabled),
+            max_entries: args.cache_max_entries.or(file.cache.max_entries).unwrap_or(defaults.cache.max_entries),
+            max_disk_bytes: args.cache_max_bytes.or(file.cache.max_disk_bytes).unwrap_or(defaults.cache.max_disk_bytes),
+            ttls: CacheTtls {
+                channel: secs(ttl.channel, default_ttls.channel),
+                resolve_url: secs(ttl.resolve_url, default_ttls.resolve_url),
+                playlist_items: secs(ttl.playlist_items, default_ttls.playlist_items),
+                subscriptions: secs(ttl.subscriptions, default_ttls.subscriptions),
+                browse: secs(ttl.browse, default_ttls.browse),
+                video: secs(ttl.video, default_ttls.video),
+                playlist: secs(ttl.playlist, default_ttls.playlist),
+                negative: secs(ttl.negative, default_ttls.negative),
+            },
+        };
+
+        let config = Self {
+            bind: args.bind.or(file.bind).unwrap_or(defaults.bind),
+            api_keys: args.api_keys
+                .or(file.api_keys)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect(),
+            daily_quota: args.daily_quota.or(file.daily_quota).unwrap_or(defaults.daily_quota),
+            data_api_url: non_empty(args.data_api_url.or(file.upstream.data_api_url))
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(defaults.data_api_url),
+            innertube_url: non_empty(args.innertube_url.or(file.upstream.innertube_url))
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or(defaults.innertube_url),
+            innertube_client_version: args.innertube_client_version
+                .or(file.upstream.innertube_client_version)
+                .unwrap_or(defaults.innertube_client_version),
+            upstream_timeout: secs(args.upstream_timeout.or(file.upstream.timeout), defaults.upstream_timeout),
+            connect_timeout: secs(args.connect_timeout.or(file.upstream.connect_timeout), defaults.connect_timeout),
+            transport,
+            retry: defaults.retry,
+            cache,
+            limits: Limits {
+                max_batch_size: args.max_batch_size.or(file.limits.max_batch_size).unwrap_or(defaults.limits.max_batch_size),
+                batch_concurrency: args.batch_concurrency.or(file.limits.batch_concurrency).unwrap_or(defaults.limits.batch_concurrency),
+                max_crawl_items: args.max_crawl_items.or(file.limits.max_crawl_items).unwrap_or(defaults.limits.max_crawl_items),
+                max_snapshots: args.max_snapshots.or(file.limits.max_snapshots).unwrap_or(defaults.limits.max_snapshots),
+            },
+            routes: routes.clone().unwrap_or(defaults.routes),
+            data_dir: non_empty(args.data_dir)
+                .map(PathBuf::from)
+                .or(file.data_dir)
+                .filter(|dir| !dir.as_os_str().is_empty()),
+            admin_token: non_empty(args.admin_token.or(file.admin_token)),
+            watch_interval: secs(args.watch_interval.or(file.watch_interval), defaults.watch_interval),
+            log: LogConfig {
+                filter: non_empty(args.log_filter.or(file.log.filter)).unwrap_or(defaults.log.filter),
+                format: args.log_format.or(file.log.format).unwrap_or(defaults.log.format),
+                dir: non_empty(args.log_dir).map(PathBuf::from).or(file.log.dir),
+                rotation: args.log_rotation.or(file.log.rotation).unwrap_or(defaults.log.rotation),
+            },
+        };
+
+        problems.extend(config.problems());
+        if routes.is_some_and(|routes| routes.contains(&RouteGroup::Admin)) && config.admin_token.is_none() {
+            problems.push("routes: admin is enabled but no admin_token is set".to_string());
+        }
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+        Ok(config)
+    }
+
+    /// Everything wrong with this configuration, so all of it can be fixed in one go
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(problem.to_string());
+            }
+        };
+
+        check(!self.api_keys.is_empty(), "api_keys: at least one key is needed, from API_KEYS, API_KEY or --api-keys");
+        check(self.daily_quota > 0, "daily_quota: must be more than 0");
+        check(is_http_url(&self.data_api_url), "data_api_url: must be an http or https URL");
+        check(is_http_url(&self.innertube_url), "innertube_url: must be an http or https URL");
+        check(
+            !self.innertube_client_version.is_empty()
+                && self.innertube_client_version.chars().all(|c| c.is_ascii_digit() || c == '.'),
+            "innertube_client_version: must look like 2.20250108.06.00",
+        );
+        check(!self.upstream_timeout.is_zero(), "upstream_timeout: must be more than 0 seconds");
+        check(!self.connect_timeout.is_zero(), "connect_timeout: must be more than 0 seconds");
+        check(self.limits.max_batch_size > 0, "max_batch_size: must be more than 0");
+        check(self.limits.batch_concurrency > 0, "batch_concurrency: must be more than 0");
+        check(self.limits.max_crawl_items > 0, "max_crawl_items: must be more than 0");
+        check(self.limits.max_snapshots > 0, "max_snapshots: must be more than 0");
+        check(
+            !self.cache.enabled || self.cache.max_entries > 0,
+            "cache_max_entries: must be more than 0 while the cache is enabled",
+        );
+        check(!self.watch_interval.is_zero(), "watch_interval: must be more than 0 seconds");
+        check(
+            tracing_subscriber::EnvFilter::try_new(&self.log.filter).is_ok(),
+            "log_filter: must be RUST_LOG style directives, e.g. info,youtube_lookup=debug",
+        );
+        check(!self.routes.is_empty(), "routes: at least one route group must be enabled");
+        check(
+            !self.data_dir.as_ref().is_some_and(|dir| dir.exists() && !dir.is_dir()),
+            "data_dir: exists but is not a directory",
+        );
+        check(
+            !self.log.dir.as_ref().is_some_and(|dir| dir.exists() && !dir.is_dir()),
+            "log_dir: exists but is not a directory",
+        );
+
+        problems
+    }
+}
+
+fn is_http_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("youtube-lookup").chain(flags.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_flags_override_config_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"
+            bind = "127.0.0.1:8080"
+            api_keys = ["from-file"]
+            routes = ["channels", "docs"]
+
+            [upstream]
+            innertube_client_version = "2.20240101"
+            timeout = 5
+
+            [limits]
+            batch_concurrency = 2
+
+            [cache.ttl]
+            channel = 60
+        "#).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let config = Config::load(args(&["--config", path, "--api-keys", "a,b", "--max-batch-size", "20"])).unwrap();
+
+        assert_eq!(config.bind, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.api_keys, ["a", "b"]);
+        assert_eq!(config.routes, [RouteGroup::Channels, RouteGroup::Docs]);
+        assert_eq!(config.innertube_client_version, "2.20240101");
+        assert_eq!(config.upstream_timeout, Duration::from_secs(5));
+        assert_eq!(config.limits.batch_concurrency, 2);
+        assert_eq!(config.limits.max_batch_size, 20);
+        assert_eq!(config.cache.ttls.channel, Duration::from_secs(60));
+        assert_eq!(config.cache.ttls.negative, CacheConfig::default().ttls.negative);
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let err = Config::load(args(&[
+            "--api-keys", "key",
+            "--data-api-url", "ftp://example.com",
+            "--batch-concurrency", "0",
+            "--routes", "channels,admin",
+        ])).unwrap_err();
+
+        let ConfigError::Invalid(problems) = err else {
+            panic!("expected validation problems, got {}", err);
+        };
+        assert_eq!(problems, [
+            "data_api_url: must be an http or https URL",
+            "batch_concurrency: must be more than 0",
+            "routes: admin is enabled but no admin_token is set",
+        ]);
+    }
+
+    #[test]
+    fn test_log_settings() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "[log]\nformat = \"json\"\ndir = \"logs\"\nrotation = \"hourly\"\n").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let config = Config::load(args(&["--config", path, "--api-keys", "key", "--log-filter", "warn,youtube_lookup=debug"])).unwrap();
+        assert_eq!(config.log.filter, "warn,youtube_lookup=debug");
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.log.dir, Some(PathBuf::from("logs")));
+        assert_eq!(config.log.rotation, LogRotation::Hourly);
+
+        let err = Config::load(args(&["--api-keys", "key", "--log-filter", "youtube_lookup=loud"])).unwrap_err();
+        assert!(err.to_string().contains("log_filter"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejects_unknown_file_settings() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "[cache]\nmax_entires = 10\n").unwrap();
+
+        let err = Config::load(args(&["--config", file.path().to_str().unwrap(), "--api-keys", "key"])).unwrap_err();
+
+        assert!(matches!(err, ConfigError::Parse { .. }), "{}", err);
+        assert!(err.to_string().contains("max_entires"), "{}", err);
+    }
+}
@@ -0,0 +1,208 @@
+use reqwest::Url;
+use serde::Serialize;
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+/// What a pasted URL or identifier points at
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(tag = "type", content = "id", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Target {
+    ChannelId(String),
+    Handle(String),
+    Username(String),
+    CustomUrl(String),
+    Vanity(String),
+    /// A URL that only `resolve_url` can map to a channel, e.g. `/c/name`
+    Url(String),
+    Video(String),
+    Playlist(String),
+}
+
+/// The interpretation chosen for some input, reported back to the caller
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Detection {
+    #[serde(flatten)]
+    pub target: Target,
+    /// Human readable name of the pattern that matched
+    pub matched: &'static str,
+}
+
+impl Detection {
+    fn new(target: Target, matched: &'static str) -> Self {
+        Self { target, matched }
+    }
+}
+
+const HOSTS: [&str; 3] = ["youtube.com", "youtube-nocookie.com", "youtu.be"];
+
+/// First path segments that are YouTube pages rather than vanity names
+const RESERVED: [&str; 12] = [
+    "watch", "playlist", "shorts", "live", "embed", "v", "channel", "user", "c", "results", "feed", "account",
+];
+
+pub(crate) fn is_channel_id(id: &str) -> bool {
+    id.len() == 24 && id.starts_with("UC") && is_id(id)
+}
+
+fn is_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Classifies a channel, video or playlist URL (with or without scheme, on
+/// any YouTube host) or a bare `@handle`, channel ID or name
+pub fn detect(input: &str) -> Option<Detection> {
+    let input = input.trim();
+
+    if let Some(handle) = input.strip_prefix('@') {
+        return is_name(handle).then(|| Detection::new(Target::Handle(handle.to_string()), "bare @handle"));
+    }
+    if is_channel_id(input) {
+        return Some(Detection::new(Target::ChannelId(input.to_string()), "bare channel ID"));
+    }
+    if is_name(input) && !input.contains('.') {
+        return Some(Detection::new(Target::Handle(input.to_string()), "bare name, tried as a handle"));
+    }
+
+    detect_url(input)
+}
+
+fn detect_url(input: &str) -> Option<Detection> {
+    let url = if input.starts_with('/') {
+        format!("https://www.youtube.com{}", input)
+    } else if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("https://{}", input)
+    };
+    let url = Url::parse(&url).ok()?;
+
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = ["www.", "m.", "music."]
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix))
+        .unwrap_or(&host);
+    if !HOSTS.contains(&host) {
+        return None;
+    }
+
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .filter(|value| is_id(value))
+    };
+
+    if host == "youtu.be" {
+        let video_id = segments.first().filter(|id| is_id(id))?;
+        return Some(Detection::new(Target::Video(video_id.to_string()), "youtu.be link"));
+    }
+
+    let detection = match segments.as_slice() {
+        ["watch", ..] => match (query("v"), query("list")) {
+            (Some(video_id), _) => Detection::new(Target::Video(video_id), "watch URL"),
+            (None, Some(playlist_id)) => Detection::new(Target::Playlist(playlist_id), "watch URL with only a playlist"),
+            (None, None) => return None,
+        },
+        ["playlist", ..] => Detection::new(Target::Playlist(query("list")?), "playlist URL"),
+        ["shorts", id, ..] if is_id(id) => Detection::new(Target::Video(id.to_string()), "Shorts URL"),
+        ["live", id, ..] if is_id(id) => Detection::new(Target::Video(id.to_string()), "live URL"),
+        ["embed" | "v", id, ..] if is_id(id) => Detection::new(Target::Video(id.to_string()), "embed URL"),
+        ["channel", id, ..] if is_channel_id(id) => Detection::new(Target::ChannelId(id.to_string()), "/channel/ URL"),
+        ["user", name, ..] if is_name(name) => Detection::new(Target::Username(name.to_string()), "/user/ URL"),
+        ["c", name, ..] if is_name(name) => Detection::new(Target::Url(format!("youtube.com/c/{}", name)), "/c/ URL"),
+        [first, ..] if first.starts_with('@') => {
+            let handle = first.trim_start_matches('@');
+            if !is_name(handle) {
+                return None;
+            }
+            Detection::new(Target::Handle(handle.to_string()), "@handle URL")
+        }
+        [first, ..] if first.starts_with('+') => {
+            let name = first.trim_start_matches('+');
+            if !is_name(name) {
+                return None;
+            }
+            Detection::new(Target::CustomUrl(name.to_string()), "+ custom URL")
+        }
+        [name, ..] if is_name(name) && !RESERVED.contains(name) => {
+            Detection::new(Target::Vanity(name.to_string()), "vanity URL")
+        }
+        _ => return None,
+    };
+
+    Some(detection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(input: &str) -> Option<Target> {
+        detect(input).map(|detection| detection.target)
+    }
+
+    #[test]
+    fn test_detect_channel_urls() {
+        let cases = [
+            ("https://youtube.com/@foo", Target::Handle("foo".to_string())),
+            ("https://www.youtube.com/@foo/videos", Target::Handle("foo".to_string())),
+            ("m.youtube.com/@foo", Target::Handle("foo".to_string())),
+            ("@foo", Target::Handle("foo".to_string())),
+            ("youtube.com/c/foo", Target::Url("youtube.com/c/foo".to_string())),
+            ("/user/foo", Target::Username("foo".to_string())),
+            ("http://www.youtube.com/user/foo?feature=share", Target::Username("foo".to_string())),
+            ("youtube.com/+foo", Target::CustomUrl("foo".to_string())),
+            ("https://www.youtube.com/foo", Target::Vanity("foo".to_string())),
+            ("https://youtube.com/channel/UCBR8-60-B28hp2BmDPdntcQ", Target::ChannelId("UCBR8-60-B28hp2BmDPdntcQ".to_string())),
+            ("UCBR8-60-B28hp2BmDPdntcQ", Target::ChannelId("UCBR8-60-B28hp2BmDPdntcQ".to_string())),
+            ("  TeamYouTube ", Target::Handle("TeamYouTube".to_string())),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(target(input), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_detect_video_and_playlist_urls() {
+        let cases = [
+            ("https://youtu.be/dQw4w9WgXcQ", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("youtu.be/dQw4w9WgXcQ?t=42", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("m.youtube.com/watch?feature=share&v=dQw4w9WgXcQ", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("https://music.youtube.com/watch?v=dQw4w9WgXcQ", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("https://youtube.com/shorts/abcdefghijk", Target::Video("abcdefghijk".to_string())),
+            ("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ", Target::Video("dQw4w9WgXcQ".to_string())),
+            ("https://www.youtube.com/playlist?list=PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb", Target::Playlist("PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb".to_string())),
+            ("youtube.com/watch?list=PL123", Target::Playlist("PL123".to_string())),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(target(input), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_detect_rejects_unrelated_input() {
+        for input in ["", "@", "https://example.com/@foo", "youtube.com/watch", "youtube.com/results?search_query=foo", "foo bar"] {
+            assert_eq!(target(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_detection_reports_match() {
+        let detection = detect("https://youtu.be/dQw4w9WgXcQ").unwrap();
+        assert_eq!(detection.matched, "youtu.be link");
+        assert_eq!(
+            serde_json::to_value(&detection).unwrap(),
+            serde_json::json!({ "type": "VIDEO", "id": "dQw4w9WgXcQ", "matched": "youtu.be link" })
+        );
+    }
+}
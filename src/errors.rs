@@ -2,7 +2,10 @@ use thiserror::Error;
 use hyper::StatusCode;
 use std::error::Error;
 
+/// Everything a lookup can fail with. New variants may be added as YouTube
+/// finds new ways to fail, so matches need a catch-all arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum YouTubeError {
     #[error("Account is closed")]
     AccountClosed,
@@ -12,6 +15,7 @@ pub enum YouTubeError {
     SubscriptionsPrivate,
     #[error("Not found")]
     NotFound,
+    /// Every API key is out of quota, or YouTube is throttling requests
     #[error("Ratelimited")]
     Ratelimited,
     #[error("Unauthorized")]
@@ -22,6 +26,8 @@ pub enum YouTubeError {
     InternalServerError,
     #[error("Unknown Status Code")]
     UnknownStatusCode(StatusCode),
+    /// The response did not have the expected shape, usually because
+    /// InnerTube changed
     #[error("Parse error")]
     ParseError(String),
     #[error("HTTP error: {0}")]
@@ -32,4 +38,26 @@ pub enum YouTubeError {
     ProtobufError(#[from] prost::DecodeError),
     #[error("Other error: {0}")]
     Other(Box<dyn Error + Send + Sync>),
-}
\ No newline at end of file
+}
+
+impl YouTubeError {
+    /// Short name of the variant, used as a metrics label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            YouTubeError::AccountClosed => "account_closed",
+            YouTubeError::AccountTerminated => "account_terminated",
+            YouTubeError::SubscriptionsPrivate => "subscriptions_private",
+            YouTubeError::NotFound => "not_found",
+            YouTubeError::Ratelimited => "ratelimited",
+            YouTubeError::Unauthorized => "unauthorized",
+            YouTubeError::Forbidden => "forbidden",
+            YouTubeError::InternalServerError => "internal_server_error",
+            YouTubeError::UnknownStatusCode(_) => "unknown_status_code",
+            YouTubeError::ParseError(_) => "parse_error",
+            YouTubeError::HttpError(_) => "http_error",
+            YouTubeError::LegacyHttpError(_) => "legacy_http_error",
+            YouTubeError::ProtobufError(_) => "protobuf_error",
+            YouTubeError::Other(_) => "other",
+        }
+    }
+}
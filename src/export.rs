@@ -0,0 +1,305 @@
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::models::{Channel, Subscription, Video, VerificationStatus};
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Csv,
+    /// JSON Lines, one object per row
+    Jsonl,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// A model that can be written as one flat row of an export
+pub trait Export {
+    type Row<'a>: Serialize where Self: 'a;
+
+    /// Column names, in the order the fields of `Row` are declared
+    const COLUMNS: &'static [&'static str];
+
+    fn row(&self) -> Self::Row<'_>;
+}
+
+/// `created_at` as an RFC 3339 UTC timestamp, for spreadsheets that can't
+/// make sense of epoch seconds
+fn rfc3339(timestamp: i64) -> Option<String> {
+    DateTime::from_timestamp(timestamp, 0).map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Lists go in a single cell
+fn join(values: &Option<Vec<String>>) -> Option<String> {
+    values.as_ref().map(|values| values.join("; "))
+}
+
+#[derive(Serialize)]
+pub struct ChannelRow<'a> {
+    user_id: &'a str,
+    display_name: Option<&'a str>,
+    description: Option<&'a str>,
+    handle: Option<&'a str>,
+    profile_picture: Option<&'a str>,
+    banner: Option<&'a str>,
+    created_at: i64,
+    created_at_rfc3339: Option<String>,
+    country: Option<&'a str>,
+    view_count: i64,
+    subscriber_count: i64,
+    video_count: i64,
+    made_for_kids: bool,
+    keywords: Option<String>,
+    trailer: Option<&'a str>,
+    analytics_account_id: Option<&'a str>,
+    conditional_redirect: Option<&'a str>,
+    no_index: Option<bool>,
+    verification: Option<&'a VerificationStatus>,
+    blocked_countries: Option<String>,
+}
+
+impl Export for Channel {
+    type Row<'a> = ChannelRow<'a>;
+
+    const COLUMNS: &'static [&'static str] = &[
+        "user_id", "display_name", "description", "handle", "profile_picture", "banner", "created_at",
+        "created_at_rfc3339", "country", "view_count", "subscriber_count", "video_count", "made_for_kids",
+        "keywords", "trailer", "analytics_account_id", "conditional_redirect", "no_index", "verification",
+        "blocked_countries",
+    ];
+
+    fn row(&self) -> ChannelRow<'_> {
+        ChannelRow {
+            user_id: &self.user_id,
+            display_name: self.display_name.as_deref(),
+            description: self.description.as_deref(),
+            handle: self.handle.as_deref(),
+            profile_picture: self.profile_picture.as_deref(),
+            banner: self.banner.as_deref(),
+            created_at: self.created_at,
+            created_at_rfc3339: rfc3339(self.created_at),
+            country: self.country.as_deref(),
+            view_count: self.view_count,
+            subscriber_count: self.subscriber_count,
+            video_count: self.video_count,
+            made_for_kids: self.made_for_kids,
+            keywords: join(&self.keywords),
+            trailer: self.trailer.as_deref(),
+            analytics_account_id: self.analytics_account_id.as_deref(),
+            conditional_redirect: self.conditional_redirect.as_deref(),
+            no_index: self.no_index,
+            verification: self.verification.as_ref(),
+            blocked_countries: join(&self.blocked_countries),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VideoRow<'a> {
+    video_id: &'a str,
+    title: &'a str,
+    description: &'a str,
+    livestream: bool,
+    views: Option<i64>,
+    likes: Option<i64>,
+    comments: Option<i64>,
+    created_at: i64,
+    created_at_rfc3339: Option<String>,
+}
+
+impl Export for Video {
+    type Row<'a> = VideoRow<'a>;
+
+    const COLUMNS: &'static [&'static str] = &[
+        "video_id", "title", "description", "livestream", "views", "likes", "comments", "created_at",
+        "created_at_rfc3339",
+    ];
+
+    fn row(&self) -> VideoRow<'_> {
+        VideoRow {
+            video_id: &self.video_id,
+            title: &self.title,
+            description: &self.description,
+            livestream: self.livestream,
+            views: self.views,
+            likes: self.likes,
+            comments: self.comments,
+            created_at: self.created_at,
+            created_at_rfc3339: rfc3339(self.created_at),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SubscriptionRow<'a> {
+    channel_id: &'a str,
+    title: &'a str,
+    created_at: i64,
+    created_at_rfc3339: Option<String>,
+    profile_picture: Option<&'a str>,
+}
+
+impl Export for Subscription {
+    type Row<'a> = SubscriptionRow<'a>;
+
+    const COLUMNS: &'static [&'static str] = &["channel_id", "title", "created_at", "created_at_rfc3339", "profile_picture"];
+
+    fn row(&self) -> SubscriptionRow<'_> {
+        SubscriptionRow {
+            channel_id: &self.channel_id,
+            title: &self.title,
+            created_at: self.created_at,
+            created_at_rfc3339: rfc3339(self.created_at),
+            profile_picture: self.profile_picture.as_deref(),
+        }
+    }
+}
+
+/// Writes `items` as a whole file. CSV always has a header row and starts
+/// with a byte order mark so Excel reads it as UTF-8; missing values are
+/// empty cells in CSV and `null` in JSON Lines.
+pub fn write<T: Export>(items: &[T], format: Format) -> Vec<u8> {
+    match format {
+        Format::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(b"\xEF\xBB\xBF".to_vec());
+            writer.write_record(T::COLUMNS).expect("writing to a Vec can't fail");
+            for item in items {
+                writer.serialize(item.row()).expect("rows are always serializable");
+            }
+            writer.into_inner().expect("writing to a Vec can't fail")
+        }
+        Format::Jsonl => {
+            let mut out = Vec::new();
+            for item in items {
+                serde_json::to_writer(&mut out, &item.row()).expect("rows are always serializable");
+                out.push(b'\n');
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(created_at: i64) -> Video {
+        Video {
+            video_id: "gfKpRpwHckY".to_string(),
+            title: "Title, with \"quotes\"".to_string(),
+            description: "Line one\nline two".to_string(),
+            livestream: false,
+            views: Some(1520),
+            likes: None,
+            comments: Some(3),
+            created_at,
+        }
+    }
+
+    fn subscription() -> Subscription {
+        Subscription {
+            channel_id: "UCBR8-60-B28hp2BmDPdntcQ".to_string(),
+            title: "YouTube".to_string(),
+            created_at: 0,
+            profile_picture: None,
+        }
+    }
+
+    fn channel() -> Channel {
+        Channel {
+            user_id: "UCBR8-60-B28hp2BmDPdntcQ".to_string(),
+            display_name: Some("YouTube".to_string()),
+            description: None,
+            handle: Some("youtube".to_string()),
+            profile_picture: None,
+            banner: None,
+            created_at: 1_130_284_800,
+            country: Some("US".to_string()),
+            view_count: 1,
+            subscriber_count: 2,
+            video_count: 3,
+            made_for_kids: false,
+            keywords: Some(vec!["music".to_string(), "videos".to_string()]),
+            trailer: None,
+            analytics_account_id: None,
+            conditional_redirect: None,
+            no_index: Some(false),
+            verification: Some(VerificationStatus::Verified),
+            blocked_countries: Some(vec![]),
+        }
+    }
+
+    /// JSON object keys are sorted, so only the set of names can be compared
+    fn assert_columns_match<T: Export>(item: &T) {
+        let serde_json::Value::Object(row) = serde_json::to_value(item.row()).unwrap() else {
+            panic!("rows serialize as objects");
+        };
+        let mut fields: Vec<&str> = row.keys().map(String::as_str).collect();
+        let mut columns = T::COLUMNS.to_vec();
+        fields.sort_unstable();
+        columns.sort_unstable();
+        assert_eq!(fields, columns);
+    }
+
+    #[test]
+    fn test_columns_match_rows() {
+        assert_columns_match(&channel());
+        assert_columns_match(&video(0));
+        assert_columns_match(&subscription());
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let csv = String::from_utf8(write(&[video(1_688_212_800)], Format::Csv)).unwrap();
+
+        let csv = csv.strip_prefix('\u{feff}').expect("starts with a byte order mark");
+        assert_eq!(
+            csv,
+            "video_id,title,description,livestream,views,likes,comments,created_at,created_at_rfc3339\n\
+             gfKpRpwHckY,\"Title, with \"\"quotes\"\"\",\"Line one\nline two\",false,1520,,3,1688212800,2023-07-01T12:00:00Z\n"
+        );
+
+        let channel = String::from_utf8(write(&[channel()], Format::Csv)).unwrap();
+        let row = channel.lines().nth(1).unwrap();
+        assert!(row.contains(",1130284800,2005-10-26T00:00:00Z,US,"), "{}", row);
+        assert!(row.contains(",music; videos,"), "{}", row);
+        assert!(row.ends_with(",false,verified,"), "{}", row);
+    }
+
+    #[test]
+    fn test_empty_csv_export_has_header() {
+        let csv = String::from_utf8(write::<Subscription>(&[], Format::Csv)).unwrap();
+        assert_eq!(csv, "\u{feff}channel_id,title,created_at,created_at_rfc3339,profile_picture\n");
+    }
+
+    #[test]
+    fn test_jsonl_export() {
+        let jsonl = String::from_utf8(write(&[subscription(), subscription()], Format::Jsonl)).unwrap();
+
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // Fields keep the column order
+        assert_eq!(
+            lines[0],
+            r#"{"channel_id":"UCBR8-60-B28hp2BmDPdntcQ","title":"YouTube","created_at":0,"created_at_rfc3339":"1970-01-01T00:00:00Z","profile_picture":null}"#
+        );
+    }
+}
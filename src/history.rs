@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use chrono::Utc;
+use redb::{ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use crate::models::Channel;
+use crate::store::{Store, StoreError};
+
+/// (channel ID, milliseconds since the epoch) to a JSON encoded `Channel`
+const SNAPSHOTS: TableDefinition<(&str, u64), &[u8]> = TableDefinition::new("channel_snapshots");
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Snapshot {
+    /// Unix timestamp of the lookup
+    pub taken_at: i64,
+    pub channel: Channel,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// What changed between two consecutive snapshots
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SnapshotDiff {
+    pub from: i64,
+    pub to: i64,
+    pub changes: Vec<FieldChange>,
+    pub subscriber_delta: i64,
+}
+
+/// Timestamped copies of every channel the service has looked up, up to
+/// `max_snapshots` per channel.
+pub struct ChannelHistory {
+    store: Arc<Store>,
+    max_snapshots: usize,
+}
+
+impl ChannelHistory {
+    pub fn new(store: Arc<Store>, max_snapshots: usize) -> Result<Self, StoreError> {
+        // Create the table up front so reads never hit a missing table
+        let txn = store.db().begin_write()?;
+        txn.open_table(SNAPSHOTS)?;
+        txn.commit()?;
+
+        Ok(Self { store, max_snapshots })
+    }
+
+    /// Stores `channel` unless it is identical to the latest snapshot, which
+    /// keeps repeated (or cached) lookups from piling up copies. The oldest
+    /// snapshots beyond `max_snapshots` are dropped. Returns whether a
+    /// snapshot was written.
+    pub fn record(&self, channel: &Channel) -> Result<bool, StoreError> {
+        let value = serde_json::to_vec(channel).expect("channels are always serializable");
+        let mut taken_at = Utc::now().timestamp_millis() as u64;
+        let id = channel.user_id.as_str();
+
+        let txn = self.store.db().begin_write()?;
+        let written = {
+            let mut table = txn.open_table(SNAPSHOTS)?;
+            if let Some(latest) = table.range((id, 0)..=(id, u64::MAX))?.next_back() {
+                let (key, latest) = latest?;
+                if latest.value() == value.as_slice() {
+                    return Ok(false);
+                }
+                // Never overwrite a snapshot taken in the same millisecond
+                taken_at = taken_at.max(key.value().1 + 1);
+            }
+            table.insert((id, taken_at), value.as_slice())?;
+
+            let excess = table.range((id, 0)..=(id, u64::MAX))?.count().saturating_sub(self.max_snapshots);
+            let mut oldest = Vec::with_capacity(excess);
+            for item in table.range((id, 0)..=(id, u64::MAX))?.take(excess) {
+                oldest.push(item?.0.value().1);
+            }
+            for taken_at in oldest {
+                table.remove((id, taken_at))?;
+            }
+            true
+        };
+        txn.commit()?;
+
+        Ok(written)
+    }
+
+    /// All snapshots of `channel_id`, oldest first
+    pub fn snapshots(&self, channel_id: &str) -> Result<Vec<Snapshot>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(SNAPSHOTS)?;
+
+        let mut snapshots = Vec::new();
+        for item in table.range((channel_id, 0)..=(channel_id, u64::MAX))? {
+            let (key, value) = item?;
+            let Ok(channel) = serde_json::from_slice(value.value()) else {
+                continue;
+            };
+            snapshots.push(Snapshot {
+                taken_at: (key.value().1 / 1000) as i64,
+                channel,
+            });
+        }
+
+        Ok(snapshots)
+    }
+}
+
+/// Field-level changes between each pair of consecutive snapshots
+pub fn diffs(snapshots: &[Snapshot]) -> Vec<SnapshotDiff> {
+    snapshots
+        .windows(2)
+        .map(|pair| diff(&pair[0], &pair[1]))
+        .collect()
+}
+
+fn diff(older: &Snapshot, newer: &Snapshot) -> SnapshotDiff {
+    let old = serde_json::to_value(&older.channel).unwrap_or_default();
+    let new = serde_json::to_value(&newer.channel).unwrap_or_default();
+
+    let changes = match (old, new) {
+        (Value::Object(old), Value::Object(new)) => new
+            .into_iter()
+            .filter_map(|(field, new)| {
+                let old = old.get(&field).cloned().unwrap_or_default();
+                (old != new).then_some(FieldChange { field, old, new })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    SnapshotDiff {
+        from: older.taken_at,
+        to: newer.taken_at,
+        changes,
+        subscriber_delta: newer.channel.subscriber_count - older.channel.subscriber_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> Channel {
+        Channel {
+            user_id: "UCBR8-60-B28hp2BmDPdntcQ".to_string(),
+            display_name: Some("YouTube".to_string()),
+            description: Some("Official channel of YouTube".to_string()),
+            handle: Some("youtube".to_string()),
+            profile_picture: None,
+            banner: Some("https://yt3.googleusercontent.com/banner_xyz=w2560".to_string()),
+            created_at: 1127083030,
+            country: Some("US".to_string()),
+            view_count: 3_100_000_000,
+            subscriber_count: 20_300_000,
+            video_count: 750,
+            made_for_kids: false,
+            keywords: None,
+            trailer: None,
+            analytics_account_id: None,
+            conditional_redirect: None,
+            no_index: Some(false),
+            verification: None,
+            blocked_countries: None,
+        }
+    }
+
+    fn history() -> ChannelHistory {
+        ChannelHistory::new(Arc::new(Store::in_memory().unwrap()), 3).unwrap()
+    }
+
+    #[test]
+    fn test_record_skips_unchanged_channels() {
+        let history = history();
+        let mut channel = channel();
+
+        assert!(history.record(&channel).unwrap());
+        assert!(!history.record(&channel).unwrap());
+
+        channel.handle = Some("youtube2".to_string());
+        assert!(history.record(&channel).unwrap());
+
+        let snapshots = history.snapshots(&channel.user_id).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].channel.handle.as_deref(), Some("youtube2"));
+        assert!(history.snapshots("UCsomeoneelse").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_drops_the_oldest_snapshots() {
+        let history = history();
+        let mut channel = channel();
+
+        for subscriber_count in 1..=5 {
+            channel.subscriber_count = subscriber_count;
+            assert!(history.record(&channel).unwrap());
+        }
+
+        let counts: Vec<i64> = history
+            .snapshots(&channel.user_id)
+            .unwrap()
+            .iter()
+            .map(|snapshot| snapshot.channel.subscriber_count)
+            .collect();
+        assert_eq!(counts, [3, 4, 5]);
+    }
+
+    #[test]
+    fn test_diffs_report_changed_fields() {
+        let before = channel();
+        let mut after = before.clone();
+        after.display_name = Some("YouTube Official".to_string());
+        after.banner = None;
+        after.subscriber_count += 1500;
+
+        let diffs = diffs(&[
+            Snapshot { taken_at: 1, channel: before },
+            Snapshot { taken_at: 2, channel: after },
+        ]);
+
+        assert_eq!(diffs.len(), 1);
+        assert_eq!((diffs[0].from, diffs[0].to), (1, 2));
+        assert_eq!(diffs[0].subscriber_delta, 1500);
+
+        let fields: Vec<&str> = diffs[0].changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["banner", "display_name", "subscriber_count"]);
+        assert_eq!(diffs[0].changes[1].old, "YouTube");
+        assert_eq!(diffs[0].changes[1].new, "YouTube Official");
+    }
+}
@@ -0,0 +1,40 @@
+//! Looks up YouTube channels by custom URL, handle, username, vanity URL,
+//! channel ID, video or playlist, combining the Data API with InnerTube for
+//! the fields only the website shows.
+//!
+//! [`Lookup`] is the place to start. The functions it wraps live in
+//! [`youtube`] (Data API) and [`youtubei`] (InnerTube) for callers that want
+//! them without the cache. The HTTP server is behind the `server` feature,
+//! which is on by default.
+
+pub mod youtubei;
+pub mod youtube;
+pub mod models;
+pub mod errors;
+#[cfg(feature = "server")]
+pub mod api;
+pub mod config;
+pub mod logging;
+pub mod transport;
+pub mod retry;
+pub mod cache;
+mod store;
+#[cfg(feature = "server")]
+mod history;
+#[cfg(feature = "server")]
+mod watchlist;
+#[cfg(feature = "server")]
+mod webhooks;
+pub mod lookup;
+pub mod detect;
+pub mod telemetry;
+#[cfg(feature = "server")]
+mod export;
+#[cfg(test)]
+mod test_support;
+
+pub use config::Config;
+pub use errors::YouTubeError;
+pub use lookup::Lookup;
+pub use models::{Channel, PlaylistSummary, Subscription, VerificationStatus, Video, VideoSummary};
+pub use youtube::channels::LookupType;
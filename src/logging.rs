@@ -0,0 +1,57 @@
+use std::io;
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use crate::config::{LogConfig, LogFormat, LogRotation};
+
+/// Installs the global subscriber: stderr, plus rolling files when
+/// `config.dir` is set. Logs written to files are flushed by a background
+/// thread until the returned guard is dropped, so it has to be kept alive.
+pub fn init(config: &LogConfig) -> Result<Option<WorkerGuard>, String> {
+    let filter = EnvFilter::try_new(&config.filter).map_err(|e| format!("Invalid log filter: {}", e))?;
+
+    let (file, guard) = match &config.dir {
+        Some(dir) => {
+            let rotation = match config.rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let appender = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix("youtube-lookup")
+                .filename_suffix("log")
+                .build(dir)
+                .map_err(|e| format!("Could not open a log file in {}: {}", dir.display(), e))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(layer(config.format, writer, false)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layer(config.format, io::stderr, true))
+        .with(file)
+        .try_init()
+        .map_err(|e| format!("Could not set up logging: {}", e))?;
+
+    Ok(guard)
+}
+
+fn layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).boxed(),
+    }
+}
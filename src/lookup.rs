@@ -0,0 +1,253 @@
+//! [`Lookup`] is the entry point for using this crate as a library: every
+//! lookup the server makes, cached, from one value.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use crate::cache::{CacheKey, Cached, ResponseCache, Resource};
+use crate::config::Config;
+use crate::errors::YouTubeError;
+use crate::models::{Channel, PlaylistSummary, Subscription, VerificationStatus, Video, VideoSummary};
+use crate::youtube::channels::{get_channel, get_channels, LookupType};
+use crate::youtube::client::YouTubeDataClient;
+use crate::youtube::keys::{KeyPool, KeyUsage};
+use crate::youtube::pages::{CrawlError, Crawled};
+use crate::youtube::playlist_items::{get_playlist_items, stream_playlist_items};
+use crate::youtube::subscriptions::{get_subscriptions, stream_subscriptions};
+use crate::youtube::playlists::get_playlist;
+use crate::youtube::videos::{get_video, populate_video_stats};
+use crate::youtubei::browse::enrich_channel_data;
+use crate::youtubei::client::YouTubeiClient;
+use crate::youtubei::resolve_url::{resolve_url, ResolveUrlResult};
+use crate::transport::Transport;
+
+pub const MAX_RESULTS: u32 = 50;
+
+/// Data API and InnerTube clients sharing one HTTP client, set up with the
+/// keys, endpoints, timeouts and retries of `config`
+pub fn clients(config: &Config) -> Result<(YouTubeDataClient, YouTubeiClient), String> {
+    let http = reqwest::Client::builder()
+        .timeout(config.upstream_timeout)
+        .connect_timeout(config.connect_timeout)
+        .build()
+        .map_err(|e| format!("Could not set up the HTTP client: {}", e))?;
+    let transport = Transport::new(http, config.transport.clone())
+        .with_retry(config.retry.clone());
+    let keys = Arc::new(KeyPool::new(config.api_keys.clone(), config.daily_quota));
+
+    Ok((
+        YouTubeDataClient::new(transport.clone(), &config.data_api_url, keys),
+        YouTubeiClient::new(transport, &config.innertube_url).with_client_version(&config.innertube_client_version),
+    ))
+}
+
+/// The InnerTube-only fields of a `Channel`, cached separately from the Data
+/// API response since they come from a different upstream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrichment {
+    pub handle: Option<String>,
+    pub conditional_redirect: Option<String>,
+    pub no_index: Option<bool>,
+    pub verification: Option<VerificationStatus>,
+    pub blocked_countries: Option<Vec<String>>,
+}
+
+impl Enrichment {
+    pub fn apply(self, channel: &mut Channel) {
+        if self.handle.is_some() {
+            channel.handle = self.handle;
+        }
+        channel.conditional_redirect = self.conditional_redirect;
+        channel.no_index = self.no_index;
+        channel.verification = self.verification;
+        channel.blocked_countries = self.blocked_countries;
+    }
+}
+
+/// Cached access to the Data API and InnerTube calls used by the handlers.
+/// Every method takes `refresh` to bypass (but still update) the cache.
+///
+/// ```no_run
+/// use youtube_lookup::{Config, Lookup, LookupType};
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = Config { api_keys: vec!["key".to_string()], ..Config::default() };
+/// let lookup = Lookup::from_config(&config)?;
+///
+/// let mut channel = lookup.channel(LookupType::Handle("youtube".to_string()), false).await?.value;
+/// lookup.enrichment(&channel, false).await?.value.apply(&mut channel);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Lookup {
+    youtube: YouTubeDataClient,
+    youtubei: YouTubeiClient,
+    cache: ResponseCache,
+}
+
+impl Lookup {
+    pub fn new(youtube: YouTubeDataClient, youtubei: YouTubeiClient, cache: ResponseCache) -> Self {
+        Self { youtube, youtubei, cache }
+    }
+
+    /// Clients set up from `config`, with a cache that lives in memory only
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let (youtube, youtubei) = clients(config)?;
+        Ok(Self::new(youtube, youtubei, ResponseCache::new(config.cache.clone())))
+    }
+
+    pub async fn channel(&self, lookup_type: LookupType, refresh: bool) -> Result<Cached<Channel>, YouTubeError> {
+        let id = match &lookup_type {
+            LookupType::ChannelID(channel_id) => channel_id.clone(),
+            // Handles and usernames are case-insensitive
+            LookupType::Handle(handle) => format!("handle:{}", handle.trim_start_matches('@').to_lowercase()),
+            LookupType::Username(username) => format!("username:{}", username.to_lowercase()),
+        };
+
+        self.cache
+            .get_or_fetch(CacheKey::new(Resource::Channel, id, None), refresh, || {
+                get_channel(&self.youtube, lookup_type)
+            })
+            .await
+    }
+
+    /// Many channels by ID, with the uncached ones fetched 50 per Data API
+    /// call. Results are in the order of `channel_ids`.
+    pub async fn channels(&self, channel_ids: &[String], refresh: bool) -> Result<Vec<Result<Cached<Channel>, YouTubeError>>, YouTubeError> {
+        let keys: Vec<CacheKey> = channel_ids
+            .iter()
+            .map(|id| CacheKey::new(Resource::Channel, id.clone(), None))
+            .collect();
+
+        self.cache
+            .get_or_fetch_many(&keys, refresh, |missing| async move {
+                let ids: Vec<String> = missing.into_iter().map(|key| key.id).collect();
+                let channels = get_channels(&self.youtube, &ids).await?;
+
+                Ok(channels
+                    .into_iter()
+                    .map(|channel| (CacheKey::new(Resource::Channel, channel.user_id.clone(), None), channel))
+                    .collect::<HashMap<_, _>>())
+            })
+            .await
+    }
+
+    pub async fn enrichment(&self, channel: &Channel, refresh: bool) -> Result<Cached<Enrichment>, YouTubeError> {
+        let key = CacheKey::new(Resource::Browse, channel.user_id.clone(), None);
+        self.cache
+            .get_or_fetch(key, refresh, || async {
+                let mut enriched = channel.clone();
+                enriched.handle = None;
+                enrich_channel_data(&self.youtubei, &mut enriched).await?;
+
+                Ok(Enrichment {
+                    handle: enriched.handle,
+                    conditional_redirect: enriched.conditional_redirect,
+                    no_index: enriched.no_index,
+                    verification: enriched.verification,
+                    blocked_countries: enriched.blocked_countries,
+                })
+            })
+            .await
+    }
+
+    pub async fn resolve_url(&self, url: String, refresh: bool) -> Result<Cached<ResolveUrlResult>, YouTubeError> {
+        let key = CacheKey::new(Resource::ResolveUrl, url.clone(), None);
+        self.cache
+            .get_or_fetch(key, refresh, || resolve_url(&self.youtubei, url))
+            .await
+    }
+
+    pub async fn video(&self, video_id: &str, refresh: bool) -> Result<Cached<VideoSummary>, YouTubeError> {
+        let key = CacheKey::new(Resource::Video, video_id, None);
+        self.cache
+            .get_or_fetch(key, refresh, || get_video(&self.youtube, video_id))
+            .await
+    }
+
+    pub async fn playlist(&self, playlist_id: &str, refresh: bool) -> Result<Cached<PlaylistSummary>, YouTubeError> {
+        let key = CacheKey::new(Resource::Playlist, playlist_id, None);
+        self.cache
+            .get_or_fetch(key, refresh, || get_playlist(&self.youtube, playlist_id))
+            .await
+    }
+
+    /// One page of uploads with view, like and comment counts filled in
+    pub async fn playlist_items(
+        &self,
+        playlist_id: &str,
+        page_token: Option<&str>,
+        refresh: bool,
+    ) -> Result<Cached<(Vec<Video>, Option<String>)>, YouTubeError> {
+        let key = CacheKey::new(Resource::PlaylistItems, playlist_id, page_token);
+        self.cache
+            .get_or_fetch(key, refresh, || async {
+                let (mut items, next_page_token) = get_playlist_items(
+                    &self.youtube,
+                    playlist_id,
+                    page_token,
+                    MAX_RESULTS,
+                ).await?;
+
+                populate_video_stats(&self.youtube, &mut items).await?;
+
+                Ok((items, next_page_token))
+            })
+            .await
+    }
+
+    pub async fn subscriptions(
+        &self,
+        channel_id: &str,
+        page_token: Option<&str>,
+        refresh: bool,
+    ) -> Result<Cached<(Vec<Subscription>, Option<String>)>, YouTubeError> {
+        let key = CacheKey::new(Resource::Subscriptions, channel_id, page_token);
+        self.cache
+            .get_or_fetch(key, refresh, || {
+                get_subscriptions(&self.youtube, channel_id, page_token, MAX_RESULTS)
+            })
+            .await
+    }
+
+    /// Every video of a playlist from `page_token` on, with stats filled in.
+    /// Crawls go straight to the Data API rather than through the cache.
+    pub fn all_playlist_items(
+        &self,
+        playlist_id: String,
+        page_token: Option<String>,
+    ) -> impl Stream<Item = Result<Crawled<Video>, CrawlError>> + Send + 'static {
+        stream_playlist_items(self.youtube.clone(), playlist_id, page_token, MAX_RESULTS)
+    }
+
+    /// Every subscription of a channel from `page_token` on
+    pub fn all_subscriptions(
+        &self,
+        channel_id: String,
+        page_token: Option<String>,
+    ) -> impl Stream<Item = Result<Crawled<Subscription>, CrawlError>> + Send + 'static {
+        stream_subscriptions(self.youtube.clone(), channel_id, page_token, MAX_RESULTS)
+    }
+
+    /// Works out why the Data API does not return `channel_id`. Subscriptions
+    /// of a removed channel fail with the reason it was removed, so this is
+    /// `AccountTerminated`, `AccountClosed`, or `NotFound` if neither applies.
+    pub async fn missing_reason(&self, channel_id: &str, refresh: bool) -> YouTubeError {
+        match self.subscriptions(channel_id, None, refresh).await {
+            Err(err @ (YouTubeError::AccountTerminated | YouTubeError::AccountClosed)) => err,
+            _ => YouTubeError::NotFound,
+        }
+    }
+
+    /// Estimated Data API quota spent and left today, per key
+    pub fn quota(&self) -> Vec<KeyUsage> {
+        self.youtube.keys().usage()
+    }
+
+    /// Drops everything cached about `channel_id`, in memory and on disk
+    pub async fn purge_channel(&self, channel_id: &str) -> Result<usize, String> {
+        self.cache.purge_channel(channel_id).await
+    }
+}
+
@@ -1,15 +1,28 @@
-mod youtubei;
-mod youtube;
-mod models;
-mod errors;
-mod api;
+use tracing::{error, info};
+use youtube_lookup::{api, config, logging};
 
 #[tokio::main]
 async fn main() {
-    let app = api::create_router();
+    let config = config::Config::load(clap::Parser::parse()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    // Kept until the server exits so buffered log lines reach the file
+    let _log_guard = logging::init(&config.log).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let bind = config.bind;
+    let app = api::create_router(config).unwrap_or_else(|e| {
+        error!("Failed to start: {}", e);
+        std::process::exit(1);
+    });
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    println!("Server starting on http://0.0.0.0:3000");
+    let listener = tokio::net::TcpListener::bind(bind).await.unwrap_or_else(|e| {
+        error!("Could not listen on {}: {}", bind, e);
+        std::process::exit(1);
+    });
+    info!("Server starting on http://{}", bind);
 
     axum::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+}
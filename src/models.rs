@@ -1,44 +1,65 @@
-use serde::Serialize;
+//! What lookups return. Timestamps are Unix seconds, and every type
+//! serializes with the field names the HTTP API uses.
 
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+/// The badge shown next to a channel's name
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq)]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum VerificationStatus {
     None,
     Verified,
+    /// Official Artist Channel
     OAC
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
 pub struct Channel {
+    /// Channel ID, starting with `UC`
     pub user_id: String,
     pub display_name: Option<String>,
     pub description: Option<String>,
+    /// Handle without the `@`
     pub handle: Option<String>,
     pub profile_picture: Option<String>,
     pub banner: Option<String>,
     pub created_at: i64,
+    /// ISO 3166-1 alpha-2 code the channel chose
     pub country: Option<String>,
     pub view_count: i64,
+    /// Rounded to three significant figures by YouTube
     pub subscriber_count: i64,
     pub video_count: i64,
     pub made_for_kids: bool,
     pub keywords: Option<Vec<String>>,
+    /// Video ID of the trailer shown to visitors who aren't subscribed
     pub trailer: Option<String>,
     pub analytics_account_id: Option<String>,
 
     // the following require innertube
+    /// Where visitors are sent instead of the channel page, if anywhere
     pub conditional_redirect: Option<String>,
+    /// Whether the channel asks search engines not to index it
     pub no_index: Option<bool>,
     pub verification: Option<VerificationStatus>,
+    /// ISO 3166-1 alpha-2 codes of the countries the channel is unavailable in
     pub blocked_countries: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// An upload of a channel. Counts are `None` when the owner hides them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
 pub struct Video {
     pub video_id: String,
     pub title: String,
     pub description: String,
+    /// Live stream or premiere, past or upcoming
     pub livestream: bool,
     pub views: Option<i64>,
     pub likes: Option<i64>,
@@ -46,10 +67,35 @@ pub struct Video {
     pub created_at: i64,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The video a channel lookup started from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct VideoSummary {
+    pub video_id: String,
+    pub channel_id: String,
+    pub title: String,
+    pub created_at: i64,
+}
+
+/// The playlist a channel lookup started from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct PlaylistSummary {
+    pub playlist_id: String,
+    pub channel_id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub video_count: Option<i64>,
+}
+
+/// A channel someone subscribes to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
 pub struct Subscription {
+    /// ID of the channel subscribed to
     pub channel_id: String,
     pub title: String,
+    /// When the subscription was made
     pub created_at: i64,
     pub profile_picture: Option<String>,
-}
\ No newline at end of file
+}
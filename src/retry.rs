@@ -0,0 +1,149 @@
+use std::time::Duration;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use crate::errors::YouTubeError;
+
+/// Exponential backoff with full jitter for one kind of upstream call.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    /// Upper bound for a single wait. A `Retry-After` longer than this is not
+    /// waited out and the error is returned instead.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const fn new(max_attempts: u32, base_delay: Duration, max_delay: Duration) -> Self {
+        Self { max_attempts, base_delay, max_delay }
+    }
+
+    /// How long to wait before attempt `attempt + 1`, or `None` if the call
+    /// should not be retried.
+    pub fn delay(&self, attempt: u32, headers: Option<&HeaderMap>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        if let Some(retry_after) = headers.and_then(retry_after) {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+
+        let exponential = self.base_delay.saturating_mul(1 << (attempt - 1).min(16));
+        let capped = exponential.min(self.max_delay);
+        Some(capped.mul_f64(rand::thread_rng().gen_range(0.5..=1.0)))
+    }
+}
+
+/// Retry limits per call type. InnerTube enrichment is best-effort, so it
+/// gives up sooner than calls whose failure fails the whole lookup.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub data_api: RetryPolicy,
+    pub browse: RetryPolicy,
+    pub resolve_url: RetryPolicy,
+    /// Webhook deliveries are retried from the outbox over hours, not inline
+    pub webhook: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            data_api: RetryPolicy::new(3, Duration::from_millis(250), Duration::from_secs(4)),
+            browse: RetryPolicy::new(2, Duration::from_millis(250), Duration::from_secs(2)),
+            resolve_url: RetryPolicy::new(3, Duration::from_millis(250), Duration::from_secs(4)),
+            webhook: RetryPolicy::new(8, Duration::from_secs(30), Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Policy for an InnerTube endpoint or Data API resource
+    pub fn policy(&self, endpoint: &str) -> &RetryPolicy {
+        match endpoint.split('?').next().unwrap_or_default() {
+            "browse" => &self.browse,
+            "navigation/resolve_url" => &self.resolve_url,
+            _ => &self.data_api,
+        }
+    }
+}
+
+/// Transient outcomes worth another attempt. Anything that describes the
+/// channel itself (terminated, closed, private, not found) is final.
+pub fn is_retryable(err: &YouTubeError) -> bool {
+    match err {
+        YouTubeError::InternalServerError | YouTubeError::Ratelimited => true,
+        YouTubeError::UnknownStatusCode(status) => is_retryable_status(*status),
+        YouTubeError::HttpError(_) | YouTubeError::LegacyHttpError(_) => true,
+        YouTubeError::Other(e) => e
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout() || e.is_connect() || e.is_request()),
+        _ => false,
+    }
+}
+
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parses a `Retry-After` header given in seconds. The HTTP-date form is not
+/// used by Google APIs and is ignored.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_delay_backs_off_exponentially() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_millis(300));
+
+        let first = policy.delay(1, None).unwrap();
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        let second = policy.delay(2, None).unwrap();
+        assert!(second >= Duration::from_millis(100) && second <= Duration::from_millis(200));
+
+        // Capped at max_delay
+        let fourth = policy.delay(4, None).unwrap();
+        assert!(fourth <= Duration::from_millis(300));
+
+        assert_eq!(policy.delay(5, None), None);
+    }
+
+    #[test]
+    fn test_delay_honors_retry_after() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(100), Duration::from_secs(5));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(policy.delay(1, Some(&headers)), Some(Duration::from_secs(2)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(policy.delay(1, Some(&headers)), None);
+    }
+
+    #[test]
+    fn test_final_outcomes_are_not_retried() {
+        assert!(is_retryable(&YouTubeError::InternalServerError));
+        assert!(is_retryable(&YouTubeError::UnknownStatusCode(StatusCode::BAD_GATEWAY)));
+        assert!(!is_retryable(&YouTubeError::AccountTerminated));
+        assert!(!is_retryable(&YouTubeError::AccountClosed));
+        assert!(!is_retryable(&YouTubeError::SubscriptionsPrivate));
+        assert!(!is_retryable(&YouTubeError::NotFound));
+        assert!(!is_retryable(&YouTubeError::ParseError("".to_string())));
+        assert!(!is_retryable(&YouTubeError::UnknownStatusCode(StatusCode::BAD_REQUEST)));
+    }
+}
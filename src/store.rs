@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::Path;
+use redb::backends::InMemoryBackend;
+use redb::Database;
+
+const DATABASE_FILE: &str = "youtube-lookup.redb";
+
+/// Any failure from the embedded database, boxed since `redb::Error` is large
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct StoreError(Box<redb::Error>);
+
+impl<E: Into<redb::Error>> From<E> for StoreError {
+    fn from(err: E) -> Self {
+        StoreError(Box::new(err.into()))
+    }
+}
+
+/// Embedded key/value database in the configured data directory. Each
+/// feature that needs to survive a restart keeps its own tables in it.
+pub struct Store {
+    db: Database,
+}
+
+impl Store {
+    /// Opens the database in `data_dir`, creating both if needed
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Could not create data directory {}: {}", data_dir.display(), e))?;
+
+        let path = data_dir.join(DATABASE_FILE);
+        let db = Database::create(&path)
+            .map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+
+        Ok(Self { db })
+    }
+
+    /// A database that lives only as long as the process, used when no data
+    /// directory is configured
+    pub fn in_memory() -> Result<Self, String> {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .map_err(|e| format!("Could not create in-memory database: {}", e))?;
+
+        Ok(Self { db })
+    }
+
+    pub fn db(&self) -> &Database {
+        &self.db
+    }
+}
@@ -0,0 +1,68 @@
+//! Prometheus metrics. Everything is recorded through the `metrics` facade,
+//! which does nothing until a recorder is installed, so library users only
+//! pay for what they collect. The server installs one and serves it at
+//! `/metrics`.
+
+use std::time::Instant;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, histogram, Unit};
+use crate::errors::YouTubeError;
+
+pub const HTTP_REQUESTS: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+pub const UPSTREAM_REQUESTS: &str = "youtube_upstream_requests_total";
+pub const UPSTREAM_REQUEST_DURATION: &str = "youtube_upstream_request_duration_seconds";
+pub const CACHE_LOOKUPS: &str = "youtube_cache_lookups_total";
+pub const QUOTA_USED: &str = "youtube_quota_used_units";
+pub const QUOTA_REMAINING: &str = "youtube_quota_remaining_units";
+
+/// Counts one upstream call by its outcome, `ok` or the `YouTubeError`
+/// variant, and records how long it took including retries
+pub(crate) fn record_upstream<T>(api: &'static str, endpoint: &str, started: Instant, result: &Result<T, YouTubeError>) {
+    let outcome = match result {
+        Ok(_) => "ok",
+        Err(e) => e.kind(),
+    };
+    counter!(UPSTREAM_REQUESTS, "api" => api, "endpoint" => endpoint.to_string(), "outcome" => outcome).increment(1);
+    histogram!(UPSTREAM_REQUEST_DURATION, "api" => api, "endpoint" => endpoint.to_string()).record(started.elapsed());
+}
+
+/// Help text for every metric, shown by `/metrics`
+pub fn describe() {
+    describe_counter!(HTTP_REQUESTS, "Requests served, by route, method and status");
+    describe_histogram!(HTTP_REQUEST_DURATION, Unit::Seconds, "Time until the response headers were sent, by route and method");
+    describe_counter!(UPSTREAM_REQUESTS, "Data API and InnerTube calls, by API, endpoint and outcome");
+    describe_histogram!(UPSTREAM_REQUEST_DURATION, Unit::Seconds, "Data API and InnerTube call latency including retries, by API and endpoint");
+    describe_counter!(CACHE_LOOKUPS, "Response cache lookups, by resource and result (hit, miss or bypass)");
+    describe_gauge!(QUOTA_USED, "Estimated Data API quota units spent today, by key index");
+    describe_gauge!(QUOTA_REMAINING, "Estimated Data API quota units left today, by key index");
+}
+
+#[cfg(feature = "server")]
+pub use server::prometheus;
+
+#[cfg(feature = "server")]
+mod server {
+    use std::sync::OnceLock;
+    use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+    /// Histogram buckets in seconds, from a cached lookup to a slow crawl page
+    const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+    /// The process-wide Prometheus recorder, installed on first use. If the
+    /// embedding application installed a recorder of its own first, that one
+    /// keeps receiving everything and this renders nothing.
+    pub fn prometheus() -> &'static PrometheusHandle {
+        static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+        HANDLE.get_or_init(|| {
+            let recorder = PrometheusBuilder::new()
+                .set_buckets(&BUCKETS)
+                .expect("buckets are not empty")
+                .build_recorder();
+            let handle = recorder.handle();
+            if metrics::set_global_recorder(recorder).is_ok() {
+                super::describe();
+            }
+            handle
+        })
+    }
+}
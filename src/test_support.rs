@@ -0,0 +1,157 @@
+//! Local stand-in for the YouTube Data API and InnerTube that serves the
+//! recorded JSON fixtures under `tests/fixtures`.
+
+use std::fs;
+use std::time::Duration;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use crate::cache::CacheConfig;
+use crate::config::Config;
+use crate::retry::{RetryConfig, RetryPolicy};
+use crate::transport::{Transport, TransportMode};
+use crate::youtube::client::YouTubeDataClient;
+use crate::youtube::keys::{KeyPool, DEFAULT_DAILY_QUOTA};
+use crate::youtubei::client::YouTubeiClient;
+
+pub const API_KEY: &str = "test-api-key";
+
+pub fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read fixture {}: {}", path, e))
+}
+
+/// Same limits as production, without the waiting
+pub fn fast_retries() -> RetryConfig {
+    let fast = |policy: RetryPolicy| RetryPolicy::new(policy.max_attempts, Duration::from_millis(1), Duration::from_millis(5));
+    let defaults = RetryConfig::default();
+    RetryConfig {
+        data_api: fast(defaults.data_api),
+        browse: fast(defaults.browse),
+        resolve_url: fast(defaults.resolve_url),
+        webhook: fast(defaults.webhook),
+    }
+}
+
+fn transport() -> Transport {
+    Transport::new(Client::new(), TransportMode::Live).with_retry(fast_retries())
+}
+
+fn json_response(status: u16, body: String) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body, "application/json")
+}
+
+pub struct MockYouTube {
+    server: MockServer,
+}
+
+impl MockYouTube {
+    pub async fn start() -> Self {
+        Self { server: MockServer::start().await }
+    }
+
+    pub fn config(&self) -> Config {
+        Config {
+            api_keys: vec![API_KEY.to_string()],
+            daily_quota: DEFAULT_DAILY_QUOTA,
+            data_api_url: format!("{}/youtube/v3", self.server.uri()),
+            innertube_url: format!("{}/youtubei/v1", self.server.uri()),
+            transport: TransportMode::Live,
+            retry: fast_retries(),
+            cache: CacheConfig::default(),
+            data_dir: None,
+            admin_token: None,
+            ..Config::default()
+        }
+    }
+
+    pub fn data_client(&self) -> YouTubeDataClient {
+        let config = self.config();
+        let keys = Arc::new(KeyPool::new(config.api_keys, config.daily_quota));
+        YouTubeDataClient::new(transport(), config.data_api_url, keys)
+    }
+
+    pub fn youtubei_client(&self) -> YouTubeiClient {
+        YouTubeiClient::new(transport(), self.config().innertube_url)
+    }
+
+    /// Serves `body` for `GET /youtube/v3/{resource}` when every `(name, value)`
+    /// in `query` is present in the request.
+    pub async fn data_api(&self, resource: &str, query: &[(&str, &str)], status: u16, body: String) {
+        self.mount_data_api(API_KEY, resource, query, status, body).await
+    }
+
+    /// Like `data_api`, but only answers requests made with `api_key`
+    pub async fn data_api_for_key(&self, api_key: &str, resource: &str, status: u16, body: String) {
+        self.mount_data_api(api_key, resource, &[], status, body).await
+    }
+
+    /// Answers consecutive requests to `request_path` with each `(status, body)`
+    /// in turn; the last response repeats.
+    pub async fn sequence(&self, request_path: &str, responses: &[(u16, String)]) {
+        for (i, (status, body)) in responses.iter().enumerate() {
+            let mock = Mock::given(path(request_path))
+                .respond_with(json_response(*status, body.clone()));
+            let mock = if i + 1 < responses.len() { mock.up_to_n_times(1) } else { mock };
+            // Earlier responses get a higher priority so they are served first
+            mock.with_priority(i as u8 + 1).mount(&self.server).await;
+        }
+    }
+
+    async fn mount_data_api(&self, api_key: &str, resource: &str, query: &[(&str, &str)], status: u16, body: String) {
+        let mut mock = Mock::given(method("GET"))
+            .and(path(format!("/youtube/v3/{}", resource)))
+            .and(header("X-Goog-Api-Key", api_key));
+        for (name, value) in query {
+            mock = mock.and(query_param(*name, *value));
+        }
+        mock.respond_with(json_response(status, body))
+            .mount(&self.server)
+            .await;
+    }
+
+    pub async fn browse(&self, browse_id: &str, status: u16, body: String) {
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/browse"))
+            .and(body_partial_json(json!({ "browseId": browse_id })))
+            .respond_with(json_response(status, body))
+            .mount(&self.server)
+            .await;
+    }
+
+    pub async fn resolve_url(&self, url: &str, status: u16, body: String) {
+        Mock::given(method("POST"))
+            .and(path("/youtubei/v1/navigation/resolve_url"))
+            .and(body_partial_json(json!({ "url": url })))
+            .respond_with(json_response(status, body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Number of requests the server received for `path`
+    pub async fn request_count(&self, request_path: &str) -> usize {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|r| r.url.path() == request_path)
+            .count()
+    }
+}
+
+pub fn browse_endpoint(browse_id: &str) -> String {
+    json!({ "endpoint": { "browseEndpoint": { "browseId": browse_id } } }).to_string()
+}
+
+// Only the handler tests resolve URLs that point off YouTube
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub fn url_endpoint(url: &str) -> String {
+    json!({ "endpoint": { "urlEndpoint": { "url": url } } }).to_string()
+}
+
+pub fn not_found() -> String {
+    json!({ "error": { "code": 404, "message": "Requested entity was not found.", "status": "NOT_FOUND" } }).to_string()
+}
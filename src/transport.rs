@@ -0,0 +1,301 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use reqwest::{Client, Request, StatusCode};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::errors::YouTubeError;
+use crate::retry::{RetryConfig, RetryPolicy};
+
+/// How upstream requests are carried out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TransportMode {
+    /// Talk to the network
+    #[default]
+    Live,
+    /// Talk to the network and write every exchange to a cassette directory
+    Record(PathBuf),
+    /// Serve every exchange from a cassette directory, never touching the network
+    Replay(PathBuf),
+}
+
+impl TransportMode {
+    /// Parses `CASSETTE_MODE` (`live`, `record` or `replay`) together with `CASSETTE_DIR`.
+    pub fn parse(mode: &str, dir: PathBuf) -> Result<Self, String> {
+        match mode {
+            "" | "live" => Ok(TransportMode::Live),
+            "record" => Ok(TransportMode::Record(dir)),
+            "replay" => Ok(TransportMode::Replay(dir)),
+            other => Err(format!("unknown cassette mode '{}', expected live, record or replay", other)),
+        }
+    }
+}
+
+/// A fully buffered upstream response.
+#[derive(Debug, Clone)]
+pub struct UpstreamResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl UpstreamResponse {
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, YouTubeError> {
+        serde_json::from_slice(&self.body).map_err(|e| YouTubeError::ParseError(e.to_string()))
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cassette {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// Shared HTTP layer under the `youtube` and `youtubei` clients.
+#[derive(Debug, Clone)]
+pub struct Transport {
+    client: Client,
+    mode: Arc<TransportMode>,
+    retry: Arc<RetryConfig>,
+}
+
+impl Transport {
+    pub fn new(client: Client, mode: TransportMode) -> Self {
+        Self {
+            client,
+            mode: Arc::new(mode),
+            retry: Arc::new(RetryConfig::default()),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Arc::new(retry);
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Retry policy for an InnerTube endpoint or Data API resource
+    pub fn retry_policy(&self, endpoint: &str) -> &RetryPolicy {
+        self.retry.policy(endpoint)
+    }
+
+    pub async fn send(&self, request: Request) -> Result<UpstreamResponse, YouTubeError> {
+        match self.mode.as_ref() {
+            TransportMode::Live => self.execute(request).await,
+            TransportMode::Record(dir) => {
+                let recorded = recorded_request(&request);
+                let response = self.execute(request).await?;
+                write_cassette(dir, recorded, &response)?;
+                Ok(response)
+            }
+            TransportMode::Replay(dir) => read_cassette(dir, &recorded_request(&request)),
+        }
+    }
+
+    async fn execute(&self, request: Request) -> Result<UpstreamResponse, YouTubeError> {
+        let resp = self.client
+            .execute(request)
+            .await
+            .map_err(|e| YouTubeError::Other(Box::new(e)))?;
+
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+
+        Ok(UpstreamResponse {
+            status,
+            headers,
+            body: body.to_vec(),
+        })
+    }
+}
+
+fn recorded_request(request: &Request) -> RecordedRequest {
+    // Only path and query are kept so that cassettes recorded against the real
+    // API replay regardless of the configured base URL.
+    let url = request.url();
+    let url = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    RecordedRequest {
+        method: request.method().to_string(),
+        url,
+        body: request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| String::from_utf8_lossy(b).into_owned()),
+    }
+}
+
+fn cassette_path(dir: &Path, request: &RecordedRequest) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(request.method.as_bytes());
+    hasher.update(request.url.as_bytes());
+    if let Some(body) = &request.body {
+        hasher.update(body.as_bytes());
+    }
+    let hash = format!("{:x}", hasher.finalize());
+
+    let endpoint = request.url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .trim_matches('/')
+        .replace(['/', '.'], "_");
+
+    dir.join(format!("{}_{}_{}.json", request.method.to_lowercase(), endpoint, &hash[..16]))
+}
+
+fn write_cassette(dir: &Path, request: RecordedRequest, response: &UpstreamResponse) -> Result<(), YouTubeError> {
+    fs::create_dir_all(dir).map_err(|e| YouTubeError::Other(Box::new(e)))?;
+
+    let path = cassette_path(dir, &request);
+    let cassette = Cassette {
+        request,
+        response: RecordedResponse {
+            status: response.status.as_u16(),
+            headers: response.headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            body: response.text(),
+        },
+    };
+
+    let json = serde_json::to_string_pretty(&cassette).map_err(|e| YouTubeError::Other(Box::new(e)))?;
+    fs::write(path, json).map_err(|e| YouTubeError::Other(Box::new(e)))
+}
+
+fn read_cassette(dir: &Path, request: &RecordedRequest) -> Result<UpstreamResponse, YouTubeError> {
+    let path = cassette_path(dir, request);
+    let json = fs::read_to_string(&path).map_err(|_| {
+        YouTubeError::Other(format!("no cassette for {} {} (expected {})", request.method, request.url, path.display()).into())
+    })?;
+
+    let cassette: Cassette = serde_json::from_str(&json)
+        .map_err(|e| YouTubeError::ParseError(format!("invalid cassette {}: {}", path.display(), e)))?;
+
+    let status = StatusCode::from_u16(cassette.response.status)
+        .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in cassette.response.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            headers.append(name, value);
+        }
+    }
+
+    Ok(UpstreamResponse {
+        status,
+        headers,
+        body: cassette.response.body.into_bytes(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::test_support::{browse_endpoint, fixture, MockYouTube, API_KEY};
+    use crate::youtube::keys::KeyPool;
+    use crate::youtube::channels::{get_channel, LookupType};
+    use crate::youtube::client::YouTubeDataClient;
+    use crate::youtubei::client::YouTubeiClient;
+    use crate::youtubei::resolve_url::{resolve_url, ResolveUrlResult};
+
+    fn keys() -> Arc<KeyPool> {
+        Arc::new(KeyPool::new(vec![API_KEY.to_string()], 10_000))
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let cassettes = tempfile::tempdir().unwrap();
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+        mock.resolve_url("youtube.com/user/YouTube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        let config = mock.config();
+
+        let record = Transport::new(Client::new(), TransportMode::Record(cassettes.path().to_path_buf()));
+        let recorded = get_channel(
+            &YouTubeDataClient::new(record.clone(), &config.data_api_url, keys()),
+            LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
+        ).await.unwrap();
+        resolve_url(&YouTubeiClient::new(record, &config.innertube_url), "youtube.com/user/YouTube".to_string()).await.unwrap();
+        assert_eq!(fs::read_dir(cassettes.path()).unwrap().count(), 2);
+
+        // The mock server is gone, so every response has to come from the cassettes
+        drop(mock);
+        let replay = Transport::new(Client::new(), TransportMode::Replay(cassettes.path().to_path_buf()));
+        let replayed = get_channel(
+            &YouTubeDataClient::new(replay.clone(), "http://127.0.0.1:9/youtube/v3", keys()),
+            LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
+        ).await.unwrap();
+        assert_eq!(replayed.user_id, recorded.user_id);
+        assert_eq!(replayed.subscriber_count, recorded.subscriber_count);
+
+        let resolved = resolve_url(
+            &YouTubeiClient::new(replay.clone(), "http://127.0.0.1:9/youtubei/v1"),
+            "youtube.com/user/YouTube".to_string(),
+        ).await;
+        assert!(matches!(resolved, Ok(ResolveUrlResult::BrowseEndpoint { browse_id }) if browse_id == "UCBR8-60-B28hp2BmDPdntcQ"));
+
+        // A request body that was never recorded is a miss rather than a network call
+        let missing = resolve_url(
+            &YouTubeiClient::new(replay, "http://127.0.0.1:9/youtubei/v1"),
+            "youtube.com/YOUTUBE".to_string(),
+        ).await;
+        assert!(matches!(missing, Err(YouTubeError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replay_preserves_error_responses() {
+        let cassettes = tempfile::tempdir().unwrap();
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[], 403, fixture("data_api/error_account_suspended.json")).await;
+        let config = mock.config();
+
+        for mode in [TransportMode::Record(cassettes.path().to_path_buf()), TransportMode::Replay(cassettes.path().to_path_buf())] {
+            let client = YouTubeDataClient::new(Transport::new(Client::new(), mode), &config.data_api_url, keys());
+            let result = crate::youtube::subscriptions::get_subscriptions(&client, "UCterminated", None, 1).await;
+            assert!(matches!(result, Err(YouTubeError::AccountTerminated)));
+        }
+        assert_eq!(mock.request_count("/youtube/v3/subscriptions").await, 1);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        let dir = PathBuf::from("cassettes");
+        assert_eq!(TransportMode::parse("", dir.clone()), Ok(TransportMode::Live));
+        assert_eq!(TransportMode::parse("record", dir.clone()), Ok(TransportMode::Record(dir.clone())));
+        assert_eq!(TransportMode::parse("replay", dir.clone()), Ok(TransportMode::Replay(dir.clone())));
+        assert!(TransportMode::parse("rewind", dir).is_err());
+    }
+}
@@ -0,0 +1,558 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use redb::{ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use tracing::{error, warn};
+use crate::detect::is_channel_id;
+use crate::errors::YouTubeError;
+use crate::lookup::Lookup;
+use crate::models::Channel;
+use crate::store::{Store, StoreError};
+use crate::webhooks::Webhooks;
+use crate::youtube::channels::LookupType;
+
+/// Channel ID to a JSON encoded `WatchedChannel`
+const WATCHLIST: TableDefinition<&str, &[u8]> = TableDefinition::new("watchlist");
+/// (milliseconds since the epoch, channel ID) to a JSON encoded `WatchEvent`
+const WATCH_EVENTS: TableDefinition<(u64, &str), &[u8]> = TableDefinition::new("watch_events");
+
+/// How often the background task looks for channels that are due
+const TICK: Duration = Duration::from_secs(60);
+/// Wait after the first failed check of a channel, doubled for each failure
+/// after it up to the watch interval
+const FIRST_RETRY: Duration = Duration::from_secs(60);
+/// Channels checked at the same time
+const CHECK_CONCURRENCY: usize = 4;
+
+/// The parts of a channel the watchlist keeps an eye on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ChannelStatus {
+    Active {
+        handle: Option<String>,
+        conditional_redirect: Option<String>,
+        no_index: Option<bool>,
+        blocked_countries: Vec<String>,
+    },
+    Terminated,
+    Closed,
+    NotFound,
+}
+
+impl ChannelStatus {
+    fn active(channel: &Channel) -> Self {
+        ChannelStatus::Active {
+            handle: channel.handle.clone(),
+            conditional_redirect: channel.conditional_redirect.clone(),
+            no_index: channel.no_index,
+            blocked_countries: channel.blocked_countries.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelChange {
+    Terminated,
+    Closed,
+    /// Gone from the Data API without being terminated or closed
+    Disappeared,
+    /// Active again after being terminated, closed or gone
+    Reinstated,
+    HandleChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    RedirectChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    NoIndexChanged {
+        old: Option<bool>,
+        new: Option<bool>,
+    },
+    BlockedCountriesChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WatchedChannel {
+    pub channel_id: String,
+    /// Unix timestamp
+    pub added_at: i64,
+    /// Unix timestamp of the last successful check
+    pub last_checked: i64,
+    pub status: ChannelStatus,
+    /// Unix timestamp of the last check, successful or not
+    #[serde(default)]
+    pub last_attempt: i64,
+    /// Checks failed in a row since the last successful one
+    #[serde(default)]
+    pub failures: u32,
+    /// Why the last check failed, while it keeps failing
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+impl WatchedChannel {
+    /// Unix timestamp from which the channel is due for a check
+    fn due_at(&self, interval: Duration) -> i64 {
+        match self.failures {
+            0 => self.last_checked + interval.as_secs() as i64,
+            failures => self.last_attempt + retry_delay(failures, interval).as_secs() as i64,
+        }
+    }
+}
+
+/// Wait before checking a channel again after `failures` failed checks in a row
+fn retry_delay(failures: u32, interval: Duration) -> Duration {
+    FIRST_RETRY
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(interval)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WatchEvent {
+    pub channel_id: String,
+    /// Unix timestamp
+    pub detected_at: i64,
+    pub changes: Vec<ChannelChange>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("Not a channel ID: {0}")]
+    InvalidChannelId(String),
+    #[error(transparent)]
+    YouTube(#[from] YouTubeError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// What changed between two checks of the same channel
+pub fn detect_changes(old: &ChannelStatus, new: &ChannelStatus) -> Vec<ChannelChange> {
+    match (old, new) {
+        (
+            ChannelStatus::Active { handle, conditional_redirect, no_index, blocked_countries },
+            ChannelStatus::Active {
+                handle: new_handle,
+                conditional_redirect: new_redirect,
+                no_index: new_no_index,
+                blocked_countries: new_blocked,
+            },
+        ) => {
+            let mut changes = Vec::new();
+            if handle != new_handle {
+                changes.push(ChannelChange::HandleChanged { old: handle.clone(), new: new_handle.clone() });
+            }
+            if conditional_redirect != new_redirect {
+                changes.push(ChannelChange::RedirectChanged {
+                    old: conditional_redirect.clone(),
+                    new: new_redirect.clone(),
+                });
+            }
+            if no_index != new_no_index {
+                changes.push(ChannelChange::NoIndexChanged { old: *no_index, new: *new_no_index });
+            }
+
+            let added: Vec<String> = new_blocked.iter().filter(|c| !blocked_countries.contains(c)).cloned().collect();
+            let removed: Vec<String> = blocked_countries.iter().filter(|c| !new_blocked.contains(c)).cloned().collect();
+            if !added.is_empty() || !removed.is_empty() {
+                changes.push(ChannelChange::BlockedCountriesChanged { added, removed });
+            }
+            changes
+        }
+        (old, new) if old == new => Vec::new(),
+        (_, ChannelStatus::Terminated) => vec![ChannelChange::Terminated],
+        (_, ChannelStatus::Closed) => vec![ChannelChange::Closed],
+        (_, ChannelStatus::NotFound) => vec![ChannelChange::Disappeared],
+        (_, ChannelStatus::Active { .. }) => vec![ChannelChange::Reinstated],
+    }
+}
+
+/// Channels registered for periodic re-checks, and the changes found so far.
+pub struct Watchlist {
+    store: Arc<Store>,
+    lookup: Arc<Lookup>,
+    webhooks: Arc<Webhooks>,
+    interval: Duration,
+}
+
+impl Watchlist {
+    pub fn new(store: Arc<Store>, lookup: Arc<Lookup>, webhooks: Arc<Webhooks>, interval: Duration) -> Result<Self, StoreError> {
+        let txn = store.db().begin_write()?;
+        txn.open_table(WATCHLIST)?;
+        txn.open_table(WATCH_EVENTS)?;
+        txn.commit()?;
+
+        Ok(Self { store, lookup, webhooks, interval })
+    }
+
+    /// Starts watching `channel_id`, checking it right away so later checks
+    /// have something to compare against
+    pub async fn add(&self, channel_id: &str) -> Result<WatchedChannel, WatchError> {
+        if !is_channel_id(channel_id) {
+            return Err(WatchError::InvalidChannelId(channel_id.to_string()));
+        }
+        if let Some(watched) = self.get(channel_id)? {
+            return Ok(watched);
+        }
+
+        let now = Utc::now().timestamp();
+        let watched = WatchedChannel {
+            channel_id: channel_id.to_string(),
+            added_at: now,
+            last_checked: now,
+            status: current_status(&self.lookup, channel_id).await?,
+            last_attempt: now,
+            failures: 0,
+            last_error: None,
+        };
+
+        self.insert(&watched)?;
+        Ok(watched)
+    }
+
+    fn insert(&self, watched: &WatchedChannel) -> Result<(), StoreError> {
+        let txn = self.store.db().begin_write()?;
+        txn.open_table(WATCHLIST)?.insert(watched.channel_id.as_str(), encode(watched).as_slice())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns whether the channel was on the watchlist
+    pub fn remove(&self, channel_id: &str) -> Result<bool, StoreError> {
+        let txn = self.store.db().begin_write()?;
+        let removed = txn.open_table(WATCHLIST)?.remove(channel_id)?.is_some();
+        txn.commit()?;
+        Ok(removed)
+    }
+
+    pub fn get(&self, channel_id: &str) -> Result<Option<WatchedChannel>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(WATCHLIST)?;
+        let watched = table.get(channel_id)?.and_then(|value| decode(value.value()));
+        Ok(watched)
+    }
+
+    pub fn list(&self) -> Result<Vec<WatchedChannel>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(WATCHLIST)?;
+
+        let mut watched = Vec::new();
+        for item in table.iter()? {
+            if let Some(channel) = decode(item?.1.value()) {
+                watched.push(channel);
+            }
+        }
+        Ok(watched)
+    }
+
+    /// The most recent `limit` events, newest first, optionally for one channel
+    pub fn events(&self, channel_id: Option<&str>, limit: usize) -> Result<Vec<WatchEvent>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(WATCH_EVENTS)?;
+
+        let mut events = Vec::new();
+        for item in table.iter()?.rev() {
+            if events.len() >= limit {
+                break;
+            }
+            let (key, value) = item?;
+            if channel_id.is_some_and(|id| id != key.value().1) {
+                continue;
+            }
+            if let Some(event) = decode(value.value()) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Re-checks every channel not checked within the interval, or due for
+    /// another try after failed checks, and returns the events recorded
+    pub async fn check_due(&self) -> Vec<WatchEvent> {
+        let now = Utc::now().timestamp();
+        let watched = match self.list() {
+            Ok(watched) => watched,
+            Err(e) => {
+                error!(error = %e, "Failed to read the watchlist");
+                return Vec::new();
+            }
+        };
+
+        let due = watched.into_iter().filter(|c| c.due_at(self.interval) <= now);
+        stream::iter(due)
+            .map(|channel| self.check(channel))
+            .buffer_unordered(CHECK_CONCURRENCY)
+            .filter_map(|event| async move { event })
+            .collect()
+            .await
+    }
+
+    /// Checks due channels in the background until the watchlist is dropped
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let watchlist = Arc::downgrade(self);
+        let mut ticks = tokio::time::interval(TICK.min(self.interval).max(Duration::from_secs(1)));
+
+        tokio::spawn(async move {
+            loop {
+                ticks.tick().await;
+                let Some(watchlist) = watchlist.upgrade() else {
+                    return;
+                };
+                watchlist.check_due().await;
+            }
+        })
+    }
+
+    async fn check(&self, watched: WatchedChannel) -> Option<WatchEvent> {
+        let channel_id = watched.channel_id.clone();
+        let recorded = match current_status(&self.lookup, &watched.channel_id).await {
+            Ok(status) => self.record(watched, status),
+            Err(e) => {
+                warn!(channel_id, failures = watched.failures + 1, error = %e, "Failed to check watched channel");
+                self.record_failure(watched, e.to_string()).map(|()| None)
+            }
+        };
+        recorded.unwrap_or_else(|e| {
+            error!(channel_id, error = %e, "Failed to store watched channel check");
+            None
+        })
+    }
+
+    fn record(&self, watched: WatchedChannel, status: ChannelStatus) -> Result<Option<WatchEvent>, StoreError> {
+        let now = Utc::now();
+        let changes = detect_changes(&watched.status, &status);
+        let event = (!changes.is_empty()).then(|| WatchEvent {
+            channel_id: watched.channel_id.clone(),
+            detected_at: now.timestamp(),
+            changes,
+        });
+
+        let updated = WatchedChannel {
+            last_checked: now.timestamp(),
+            status,
+            last_attempt: now.timestamp(),
+            failures: 0,
+            last_error: None,
+            ..watched
+        };
+
+        let txn = self.store.db().begin_write()?;
+        {
+            let mut watchlist = txn.open_table(WATCHLIST)?;
+            if !self.update(&mut watchlist, &updated)? {
+                return Ok(None);
+            }
+
+            if let Some(event) = &event {
+                let key = (now.timestamp_millis() as u64, updated.channel_id.as_str());
+                txn.open_table(WATCH_EVENTS)?.insert(key, encode(event).as_slice())?;
+                self.webhooks.enqueue(&txn, event)?;
+            }
+        }
+        txn.commit()?;
+
+        Ok(event)
+    }
+
+    /// Keeps the last status and pushes the next check back
+    fn record_failure(&self, watched: WatchedChannel, error: String) -> Result<(), StoreError> {
+        let updated = WatchedChannel {
+            last_attempt: Utc::now().timestamp(),
+            failures: watched.failures.saturating_add(1),
+            last_error: Some(error),
+            ..watched
+        };
+
+        let txn = self.store.db().begin_write()?;
+        self.update(&mut txn.open_table(WATCHLIST)?, &updated)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Stores `watched` unless it was removed while it was being checked, and
+    /// returns whether it was stored
+    fn update(&self, watchlist: &mut redb::Table<&str, &[u8]>, watched: &WatchedChannel) -> Result<bool, StoreError> {
+        if watchlist.get(watched.channel_id.as_str())?.is_none() {
+            return Ok(false);
+        }
+        watchlist.insert(watched.channel_id.as_str(), encode(watched).as_slice())?;
+        Ok(true)
+    }
+}
+
+/// Fetches the channel from upstream, bypassing the cache
+async fn current_status(lookup: &Lookup, channel_id: &str) -> Result<ChannelStatus, YouTubeError> {
+    match lookup.channel(LookupType::ChannelID(channel_id.to_string()), true).await {
+        Ok(cached) => {
+            let mut channel = cached.value;
+            // Unlike lookups, a failed enrichment fails the check, since the
+            // missing fields would otherwise show up as changes
+            lookup.enrichment(&channel, true).await?.value.apply(&mut channel);
+            Ok(ChannelStatus::active(&channel))
+        }
+        Err(YouTubeError::NotFound) => Ok(match lookup.missing_reason(channel_id, true).await {
+            YouTubeError::AccountTerminated => ChannelStatus::Terminated,
+            YouTubeError::AccountClosed => ChannelStatus::Closed,
+            _ => ChannelStatus::NotFound,
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).expect("watchlist records are always serializable")
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(value: &[u8]) -> Option<T> {
+    serde_json::from_slice(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CacheConfig, ResponseCache};
+    use crate::test_support::{browse_endpoint, fast_retries, fixture, MockYouTube};
+
+    fn active(handle: &str, blocked: &[&str]) -> ChannelStatus {
+        ChannelStatus::Active {
+            handle: Some(handle.to_string()),
+            conditional_redirect: None,
+            no_index: Some(false),
+            blocked_countries: blocked.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn watchlist(mock: &MockYouTube) -> Watchlist {
+        let lookup = Lookup::new(
+            mock.data_client(),
+            mock.youtubei_client(),
+            ResponseCache::new(CacheConfig::default()),
+        );
+        let store = Arc::new(Store::in_memory().unwrap());
+        let webhooks = Arc::new(Webhooks::new(store.clone(), fast_retries().webhook).unwrap());
+        Watchlist::new(store, Arc::new(lookup), webhooks, Duration::ZERO).unwrap()
+    }
+
+    #[test]
+    fn test_detect_changes_between_active_states() {
+        let old = active("youtube", &["DE"]);
+        let new = ChannelStatus::Active {
+            handle: Some("youtubeofficial".to_string()),
+            conditional_redirect: Some("https://www.youtube.com/@youtubeofficial".to_string()),
+            no_index: Some(true),
+            blocked_countries: vec!["FR".to_string()],
+        };
+
+        assert_eq!(detect_changes(&old, &new), vec![
+            ChannelChange::HandleChanged { old: Some("youtube".to_string()), new: Some("youtubeofficial".to_string()) },
+            ChannelChange::RedirectChanged { old: None, new: Some("https://www.youtube.com/@youtubeofficial".to_string()) },
+            ChannelChange::NoIndexChanged { old: Some(false), new: Some(true) },
+            ChannelChange::BlockedCountriesChanged { added: vec!["FR".to_string()], removed: vec!["DE".to_string()] },
+        ]);
+        assert!(detect_changes(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_detect_changes_in_availability() {
+        let active = active("youtube", &[]);
+
+        assert_eq!(detect_changes(&active, &ChannelStatus::Terminated), vec![ChannelChange::Terminated]);
+        assert_eq!(detect_changes(&active, &ChannelStatus::Closed), vec![ChannelChange::Closed]);
+        assert_eq!(detect_changes(&active, &ChannelStatus::NotFound), vec![ChannelChange::Disappeared]);
+        assert_eq!(detect_changes(&ChannelStatus::NotFound, &active), vec![ChannelChange::Reinstated]);
+        assert!(detect_changes(&ChannelStatus::Terminated, &ChannelStatus::Terminated).is_empty());
+    }
+
+    #[test]
+    fn test_failed_checks_back_off() {
+        let interval = Duration::from_secs(6 * 60 * 60);
+        assert_eq!(retry_delay(1, interval), FIRST_RETRY);
+        assert_eq!(retry_delay(3, interval), FIRST_RETRY * 4);
+        assert_eq!(retry_delay(20, interval), interval);
+        assert_eq!(retry_delay(u32::MAX, interval), interval);
+
+        let watched = WatchedChannel {
+            channel_id: "UCBR8-60-B28hp2BmDPdntcQ".to_string(),
+            added_at: 0,
+            last_checked: 0,
+            status: ChannelStatus::NotFound,
+            last_attempt: 1000,
+            failures: 2,
+            last_error: Some("Internal server error".to_string()),
+        };
+        assert_eq!(watched.due_at(interval), 1000 + 2 * FIRST_RETRY.as_secs() as i64);
+        assert_eq!(WatchedChannel { failures: 0, ..watched }.due_at(interval), interval.as_secs() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_add_rejects_other_identifiers() {
+        let mock = MockYouTube::start().await;
+        let watchlist = watchlist(&mock);
+
+        for id in ["@youtube", "UCshort", "https://www.youtube.com/channel/UCBR8-60-B28hp2BmDPdntcQ"] {
+            assert!(matches!(watchlist.add(id).await, Err(WatchError::InvalidChannelId(_))));
+        }
+        assert!(watchlist.list().unwrap().is_empty());
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_due_records_termination() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/channels", &[
+            (200, fixture("data_api/channel_youtube.json")),
+            (200, fixture("data_api/channel_empty.json")),
+        ]).await;
+        mock.data_api("subscriptions", &[("channelId", "UCBR8-60-B28hp2BmDPdntcQ")], 403, fixture("data_api/error_account_suspended.json")).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, fixture("innertube/browse_verified.json")).await;
+        mock.resolve_url("youtube.com/@youtube", 200, browse_endpoint("UCBR8-60-B28hp2BmDPdntcQ")).await;
+        let watchlist = watchlist(&mock);
+
+        let watched = watchlist.add("UCBR8-60-B28hp2BmDPdntcQ").await.unwrap();
+        assert!(matches!(watched.status, ChannelStatus::Active { .. }));
+
+        let events = watchlist.check_due().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].changes, vec![ChannelChange::Terminated]);
+
+        // Nothing changes on the next check
+        assert!(watchlist.check_due().await.is_empty());
+
+        let stored = watchlist.events(Some("UCBR8-60-B28hp2BmDPdntcQ"), 10).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert!(watchlist.events(Some("UCsomeoneelse"), 10).unwrap().is_empty());
+        assert_eq!(watchlist.get("UCBR8-60-B28hp2BmDPdntcQ").unwrap().unwrap().status, ChannelStatus::Terminated);
+    }
+
+    #[tokio::test]
+    async fn test_failed_checks_keep_the_previous_status() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/channels", &[
+            (200, fixture("data_api/channel_youtube.json")),
+            (500, fixture("data_api/error_backend.json")),
+        ]).await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, fixture("innertube/browse_verified.json")).await;
+        let watchlist = watchlist(&mock);
+
+        watchlist.add("UCBR8-60-B28hp2BmDPdntcQ").await.unwrap();
+
+        assert!(watchlist.check_due().await.is_empty());
+        assert!(watchlist.check_due().await.is_empty());
+        let watched = watchlist.get("UCBR8-60-B28hp2BmDPdntcQ").unwrap().unwrap();
+        assert!(matches!(watched.status, ChannelStatus::Active { .. }));
+        assert_eq!(watched.failures, 2);
+        assert!(watched.last_error.is_some());
+        assert!(watched.last_attempt >= watched.last_checked);
+
+        assert!(watchlist.remove("UCBR8-60-B28hp2BmDPdntcQ").unwrap());
+        assert!(watchlist.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use redb::{ReadableTable, TableDefinition, WriteTransaction};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+use sha2::Sha256;
+use tracing::error;
+use crate::retry::RetryPolicy;
+use crate::store::{Store, StoreError};
+use crate::watchlist::WatchEvent;
+
+/// Webhook ID to a JSON encoded `WebhookRecord`
+const WEBHOOKS: TableDefinition<&str, &[u8]> = TableDefinition::new("webhooks");
+/// Delivery ID to a JSON encoded `Delivery`, until it succeeds
+const OUTBOX: TableDefinition<u64, &[u8]> = TableDefinition::new("webhook_outbox");
+/// Counters that have to outlive the rows they number
+const COUNTERS: TableDefinition<&str, u64> = TableDefinition::new("webhook_counters");
+/// Last delivery ID handed out. Finished deliveries leave the outbox, so its
+/// last key alone would hand their IDs out again.
+const LAST_DELIVERY_ID: &str = "last_delivery_id";
+
+/// How often the background task looks for deliveries that are due
+const TICK: Duration = Duration::from_secs(5);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WebhookRecord {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+/// Returned once when a webhook is created; the secret is not shown again
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    /// Out of attempts. Kept in the outbox so it can be inspected.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Delivery {
+    pub id: u64,
+    pub webhook_id: String,
+    pub url: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// Unix timestamp
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook URL: {0}")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `{timestamp}.{body}`
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+/// Registered webhooks and a persistent outbox of watchlist events to send
+/// them. Deliveries are retried with backoff until they succeed or run out
+/// of attempts.
+pub struct Webhooks {
+    store: Arc<Store>,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl Webhooks {
+    pub fn new(store: Arc<Store>, retry: RetryPolicy) -> Result<Self, StoreError> {
+        let txn = store.db().begin_write()?;
+        txn.open_table(WEBHOOKS)?;
+        txn.open_table(OUTBOX)?;
+        txn.open_table(COUNTERS)?;
+        txn.commit()?;
+
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .expect("default reqwest client");
+
+        Ok(Self { store, client, retry })
+    }
+
+    pub fn create(&self, url: &str) -> Result<CreatedWebhook, WebhookError> {
+        let parsed = Url::parse(url).map_err(|e| WebhookError::InvalidUrl(e.to_string()))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(WebhookError::InvalidUrl("must be http or https".to_string()));
+        }
+
+        let record = WebhookRecord {
+            webhook: Webhook {
+                id: random_hex(8),
+                url: parsed.to_string(),
+                created_at: Utc::now().timestamp(),
+            },
+            secret: random_hex(32),
+        };
+
+        self.insert(&record)?;
+
+        Ok(CreatedWebhook {
+            webhook: record.webhook,
+            secret: record.secret,
+        })
+    }
+
+    fn insert(&self, record: &WebhookRecord) -> Result<(), StoreError> {
+        let txn = self.store.db().begin_write()?;
+        txn.open_table(WEBHOOKS)?.insert(record.webhook.id.as_str(), encode(record).as_slice())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<Webhook>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(WEBHOOKS)?;
+
+        let mut webhooks = Vec::new();
+        for item in table.iter()? {
+            if let Some(record) = decode::<WebhookRecord>(item?.1.value()) {
+                webhooks.push(record.webhook);
+            }
+        }
+        Ok(webhooks)
+    }
+
+    /// Returns whether the webhook existed. Its pending deliveries are dropped
+    /// the next time they come up.
+    pub fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        let txn = self.store.db().begin_write()?;
+        let removed = txn.open_table(WEBHOOKS)?.remove(id)?.is_some();
+        txn.commit()?;
+        Ok(removed)
+    }
+
+    /// Queues `event` for every webhook as part of `txn`, so the event and its
+    /// deliveries are stored together
+    pub fn enqueue(&self, txn: &WriteTransaction, event: &WatchEvent) -> Result<(), StoreError> {
+        let webhooks: Vec<WebhookRecord> = {
+            let table = txn.open_table(WEBHOOKS)?;
+            let mut webhooks = Vec::new();
+            for item in table.iter()? {
+                webhooks.extend(decode(item?.1.value()));
+            }
+            webhooks
+        };
+
+        let mut outbox = txn.open_table(OUTBOX)?;
+        let mut counters = txn.open_table(COUNTERS)?;
+        let last_id = counters.get(LAST_DELIVERY_ID)?.map(|id| id.value()).unwrap_or(0);
+        let now = Utc::now().timestamp();
+
+        let mut next_id = last_id;
+        for record in webhooks {
+            next_id += 1;
+            let id = next_id;
+            let delivery = Delivery {
+                id,
+                webhook_id: record.webhook.id,
+                url: record.webhook.url,
+                payload: json!({
+                    "id": id,
+                    "type": "watchlist.change",
+                    "data": event,
+                }),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                created_at: now,
+            };
+            outbox.insert(id, encode(&delivery).as_slice())?;
+        }
+        if next_id != last_id {
+            counters.insert(LAST_DELIVERY_ID, next_id)?;
+        }
+        Ok(())
+    }
+
+    /// Deliveries still in the outbox, optionally only those with `status`
+    pub fn deliveries(&self, status: Option<DeliveryStatus>) -> Result<Vec<Delivery>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(OUTBOX)?;
+
+        let mut deliveries = Vec::new();
+        for item in table.iter()? {
+            let Some(delivery) = decode::<Delivery>(item?.1.value()) else {
+                continue;
+            };
+            if status.is_some_and(|status| status != delivery.status) {
+                continue;
+            }
+            deliveries.push(delivery);
+        }
+        Ok(deliveries)
+    }
+
+    /// Attempts every pending delivery that is due and returns how many
+    /// succeeded
+    pub async fn deliver_due(&self) -> usize {
+        let now = Utc::now().timestamp();
+        let due = match self.deliveries(Some(DeliveryStatus::Pending)) {
+            Ok(deliveries) => deliveries.into_iter().filter(|d| d.next_attempt_at <= now),
+            Err(e) => {
+                error!(error = %e, "Failed to read the webhook outbox");
+                return 0;
+            }
+        };
+
+        let mut delivered = 0;
+        for delivery in due {
+            let id = delivery.id;
+            match self.attempt(delivery).await {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => error!(delivery_id = id, error = %e, "Failed to update webhook delivery"),
+            }
+        }
+        delivered
+    }
+
+    /// Delivers due events in the background until the webhooks are dropped
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let webhooks = Arc::downgrade(self);
+        let mut ticks = tokio::time::interval(TICK);
+
+        tokio::spawn(async move {
+            loop {
+                ticks.tick().await;
+                let Some(webhooks) = webhooks.upgrade() else {
+                    return;
+                };
+                webhooks.deliver_due().await;
+            }
+        })
+    }
+
+    async fn attempt(&self, mut delivery: Delivery) -> Result<bool, StoreError> {
+        let Some(secret) = self.secret(&delivery.webhook_id)? else {
+            // The webhook was removed
+            self.finish(delivery.id)?;
+            return Ok(false);
+        };
+
+        match self.send(&delivery, &secret).await {
+            Ok(()) => {
+                self.finish(delivery.id)?;
+                Ok(true)
+            }
+            Err(e) => {
+                delivery.attempts += 1;
+                delivery.last_error = Some(e);
+                match self.retry.delay(delivery.attempts, None) {
+                    Some(delay) => {
+                        delivery.next_attempt_at = Utc::now().timestamp() + delay.as_secs() as i64;
+                    }
+                    None => delivery.status = DeliveryStatus::Failed,
+                }
+                self.update(&delivery)?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn send(&self, delivery: &Delivery, secret: &str) -> Result<(), String> {
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| e.to_string())?;
+        let timestamp = Utc::now().timestamp();
+
+        let resp = self.client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature(secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !resp.status().is_success() {
+            return Err(format!("HTTP {}", resp.status()));
+        }
+        Ok(())
+    }
+
+    fn secret(&self, webhook_id: &str) -> Result<Option<String>, StoreError> {
+        let txn = self.store.db().begin_read()?;
+        let table = txn.open_table(WEBHOOKS)?;
+        let record = table.get(webhook_id)?.and_then(|value| decode::<WebhookRecord>(value.value()));
+        Ok(record.map(|record| record.secret))
+    }
+
+    fn update(&self, delivery: &Delivery) -> Result<(), StoreError> {
+        let txn = self.store.db().begin_write()?;
+        txn.open_table(OUTBOX)?.insert(delivery.id, encode(delivery).as_slice())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn finish(&self, delivery_id: u64) -> Result<(), StoreError> {
+        let txn = self.store.db().begin_write()?;
+        txn.open_table(OUTBOX)?.remove(delivery_id)?;
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).expect("webhook records are always serializable")
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(value: &[u8]) -> Option<T> {
+    serde_json::from_slice(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::fast_retries;
+    use crate::watchlist::ChannelChange;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn webhooks() -> Webhooks {
+        Webhooks::new(Arc::new(Store::in_memory().unwrap()), fast_retries().webhook).unwrap()
+    }
+
+    fn enqueue(webhooks: &Webhooks) {
+        let event = WatchEvent {
+            channel_id: "UCBR8-60-B28hp2BmDPdntcQ".to_string(),
+            detected_at: 1735689600,
+            changes: vec![ChannelChange::Terminated],
+        };
+        let txn = webhooks.store.db().begin_write().unwrap();
+        webhooks.enqueue(&txn, &event).unwrap();
+        txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_signature_format() {
+        let signature = signature("secret", 1735689600, b"{}");
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(signature, super::signature("other", 1735689600, b"{}"));
+    }
+
+    #[test]
+    fn test_create_rejects_invalid_urls() {
+        let webhooks = webhooks();
+        assert!(matches!(webhooks.create("not a url"), Err(WebhookError::InvalidUrl(_))));
+        assert!(matches!(webhooks.create("ftp://example.com/hook"), Err(WebhookError::InvalidUrl(_))));
+        assert!(webhooks.list().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_removed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        let webhooks = webhooks();
+        let created = webhooks.create(&format!("{}/hook", server.uri())).unwrap();
+        enqueue(&webhooks);
+
+        assert_eq!(webhooks.deliver_due().await, 1);
+        assert!(webhooks.deliveries(None).unwrap().is_empty());
+
+        let requests = server.received_requests().await.unwrap();
+        let request = &requests[0];
+        let timestamp: i64 = request.headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            request.headers[SIGNATURE_HEADER].to_str().unwrap(),
+            signature(&created.secret, timestamp, &request.body)
+        );
+
+        let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload["type"], "watchlist.change");
+        assert_eq!(payload["data"]["changes"][0]["type"], "terminated");
+    }
+
+    #[tokio::test]
+    async fn test_delivery_ids_are_not_reused() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        let webhooks = webhooks();
+        webhooks.create(&format!("{}/hook", server.uri())).unwrap();
+
+        enqueue(&webhooks);
+        let first = webhooks.deliveries(None).unwrap()[0].id;
+        assert_eq!(webhooks.deliver_due().await, 1);
+        assert!(webhooks.deliveries(None).unwrap().is_empty());
+
+        enqueue(&webhooks);
+        let second = webhooks.deliveries(None).unwrap()[0].id;
+        assert!(second > first);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_kept_after_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        let webhooks = webhooks();
+        webhooks.create(&format!("{}/hook", server.uri())).unwrap();
+        enqueue(&webhooks);
+
+        for _ in 0..fast_retries().webhook.max_attempts {
+            assert_eq!(webhooks.deliver_due().await, 0);
+        }
+
+        let failed = webhooks.deliveries(Some(DeliveryStatus::Failed)).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, fast_retries().webhook.max_attempts);
+        assert_eq!(failed[0].last_error.as_deref(), Some("HTTP 500 Internal Server Error"));
+        assert_eq!(server.received_requests().await.unwrap().len(), 8);
+    }
+}
@@ -1,8 +1,8 @@
-use reqwest::Client;
 use serde::Deserialize;
 use chrono::DateTime;
 use crate::models::Channel;
 use crate::errors::YouTubeError;
+use super::client::YouTubeDataClient;
 
 #[derive(Debug)]
 pub enum LookupType {
@@ -85,86 +85,55 @@ struct ChannelImage {
     banner_external_url: Option<String>
 }
 
-#[derive(Debug, Deserialize)]
-struct ErrorResponse {
-    error: Error,
-}
+const PARTS: &str = "brandingSettings,id,snippet,statistics,status,localizations,topicDetails";
+const FIELDS: &str = "items(id,snippet(title,description,customUrl,publishedAt,country,thumbnails.default.url),statistics(subscriberCount,viewCount,videoCount),topicDetails.topicIds,brandingSettings(channel(keywords,unsubscribedTrailer,trackingAnalyticsAccountId),image.bannerExternalUrl),status.madeForKids)";
 
-#[derive(Debug, Deserialize)]
-struct Error {
-    message: String,
-}
+/// Most IDs the Data API accepts in a single `channels` request
+const MAX_IDS_PER_REQUEST: usize = 50;
 
 pub async fn get_channel(
-    client: &Client,
+    client: &YouTubeDataClient,
     lookup_type: LookupType,
-    api_key: &str,
 ) -> Result<Channel, YouTubeError> {
-    let url = match &lookup_type {
-        LookupType::Username(username) => format!(
-            "https://youtube.googleapis.com/youtube/v3/channels?part=brandingSettings,id,snippet,statistics,status,localizations,topicDetails&forUsername={}",
-            username
-        ),
-        LookupType::Handle(handle) => format!(
-            "https://youtube.googleapis.com/youtube/v3/channels?part=brandingSettings,id,snippet,statistics,status,localizations,topicDetails&forHandle={}",
-            handle
-        ),
-        LookupType::ChannelID(channel_id) => format!(
-            "https://youtube.googleapis.com/youtube/v3/channels?part=brandingSettings,id,snippet,statistics,status,localizations,topicDetails&id={}",
-            channel_id
-        ),
+    let filter = match &lookup_type {
+        LookupType::Username(username) => ("forUsername", username.as_str()),
+        // Handles are accepted with or without the `@`
+        LookupType::Handle(handle) => ("forHandle", handle.strip_prefix('@').unwrap_or(handle)),
+        LookupType::ChannelID(channel_id) => ("id", channel_id.as_str()),
     };
 
-    let mut request = client
-        .get(&url)
-        .header("Host", "youtube.googleapis.com")
-        .header("X-Goog-Fieldmask", "items(id,snippet(title,description,customUrl,publishedAt,country,thumbnails.default.url),statistics(subscriberCount,viewCount,videoCount),topicDetails.topicIds,brandingSettings(channel(keywords,unsubscribedTrailer,trackingAnalyticsAccountId),image.bannerExternalUrl),status.madeForKids)");
-
-    request = request.header("X-Goog-Api-Key", api_key);
-
-    let resp = request
-        .send()
-        .await
-        .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-    match resp.status() {
-        reqwest::StatusCode::TOO_MANY_REQUESTS => return Err(YouTubeError::Ratelimited),
-        reqwest::StatusCode::FORBIDDEN => {
-            let error_response: ErrorResponse = resp
-                .json()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            
-            if error_response.error.message.starts_with("The request cannot be completed because you have exceeded your") {
-                return Err(YouTubeError::Ratelimited);
-            }
-            return Err(YouTubeError::Forbidden);
-        },
-        reqwest::StatusCode::NOT_FOUND => return Err(YouTubeError::NotFound),
-        reqwest::StatusCode::UNAUTHORIZED => return Err(YouTubeError::Unauthorized),
-        reqwest::StatusCode::INTERNAL_SERVER_ERROR | reqwest::StatusCode::SERVICE_UNAVAILABLE => {
-            return Err(YouTubeError::InternalServerError);
-        },
-        reqwest::StatusCode::OK => (), // Continue processing
-        status => {
-            let body = resp
-                .text()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            eprintln!("Unknown status code {}: {}", status.as_u16(), body);
-            return Err(YouTubeError::UnknownStatusCode(status));
-        }
-    }
-
-    let api_response: ApiResponse = resp
-        .json()
-        .await
-        .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+    let api_response: ApiResponse = client
+        .get("channels", &[("part", PARTS), filter], FIELDS)
+        .await?;
 
     let channel = api_response.items
         .and_then(|mut items| items.pop())
         .ok_or(YouTubeError::NotFound)?;
 
+    Ok(into_channel(channel))
+}
+
+/// Fetches many channels by ID, 50 per request. Channels that do not exist
+/// (or were removed) are simply missing from the result.
+pub async fn get_channels(
+    client: &YouTubeDataClient,
+    channel_ids: &[String],
+) -> Result<Vec<Channel>, YouTubeError> {
+    let mut channels = Vec::with_capacity(channel_ids.len());
+
+    for chunk in channel_ids.chunks(MAX_IDS_PER_REQUEST) {
+        let ids = chunk.join(",");
+        let api_response: ApiResponse = client
+            .get("channels", &[("part", PARTS), ("id", ids.as_str())], FIELDS)
+            .await?;
+
+        channels.extend(api_response.items.unwrap_or_default().into_iter().map(into_channel));
+    }
+
+    Ok(channels)
+}
+
+fn into_channel(channel: ApiChannel) -> Channel {
     let profile_picture = channel.snippet
         .as_ref()
         .and_then(|s| s.thumbnails.as_ref())
@@ -210,7 +179,7 @@ pub async fn get_channel(
             }
         });
 
-    Ok(Channel {
+    Channel {
         user_id: channel.id,
         display_name: channel.snippet.as_ref().and_then(|s| s.title.clone()),
         description: channel.snippet.as_ref().and_then(|s| s.description.clone()),
@@ -285,52 +254,135 @@ pub async fn get_channel(
         conditional_redirect: None,
         no_index: None,
         verification: None
-    })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    
-    fn get_api_key() -> String {
-        dotenvy::dotenv().ok();
-        env::var("API_KEY").expect("API_KEY must be set")
-    }
+    use crate::test_support::{fixture, MockYouTube};
 
     #[tokio::test]
     async fn test_get_channel_by_id() {
-        let client = Client::new();
-        let result = get_channel(
-            &client,
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCBR8-60-B28hp2BmDPdntcQ")], 200, fixture("data_api/channel_youtube.json")).await;
+
+        let channel = get_channel(
+            &mock.data_client(),
             LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
-            &get_api_key(),
-        ).await;
+        ).await.unwrap();
 
-        assert!(result.is_ok());
+        assert_eq!(channel.user_id, "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(channel.display_name.as_deref(), Some("YouTube"));
+        assert_eq!(channel.handle.as_deref(), Some("youtube"));
+        assert_eq!(channel.profile_picture.as_deref(), Some("ytc/AIdro_abc123"));
+        assert_eq!(channel.banner.as_deref(), Some("banner_xyz"));
+        assert_eq!(channel.created_at, 1127083030);
+        assert_eq!(channel.country.as_deref(), Some("US"));
+        assert_eq!(channel.subscriber_count, 20300000);
+        assert_eq!(channel.view_count, 3100000000);
+        assert_eq!(channel.video_count, 750);
+        assert_eq!(channel.keywords, Some(vec!["youtube".to_string(), "creator tools".to_string(), "music".to_string()]));
+        assert_eq!(channel.trailer.as_deref(), Some("Qc3Uq9VmRKM"));
+        assert!(!channel.made_for_kids);
     }
 
     #[tokio::test]
     async fn test_get_channel_by_username() {
-        let client = Client::new();
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("forUsername", "YouTube")], 200, fixture("data_api/channel_youtube.json")).await;
+
         let result = get_channel(
-            &client,
+            &mock.data_client(),
             LookupType::Username("YouTube".to_string()),
-            &get_api_key(),
         ).await;
 
-        assert!(result.is_ok());
+        assert_eq!(result.unwrap().user_id, "UCBR8-60-B28hp2BmDPdntcQ");
     }
 
     #[tokio::test]
     async fn test_get_channel_by_handle() {
-        let client = Client::new();
-        let result = get_channel(
-            &client,
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("forHandle", "TeamYouTube")], 200, fixture("data_api/channel_team_youtube.json")).await;
+
+        let channel = get_channel(
+            &mock.data_client(),
             LookupType::Handle("TeamYouTube".to_string()),
-            &get_api_key(),
+        ).await.unwrap();
+
+        assert_eq!(channel.user_id, "UCK8sQmJBp8GCxrOtXWBpyEA");
+        assert_eq!(channel.handle.as_deref(), Some("teamyoutube"));
+        assert_eq!(channel.keywords, None);
+
+        let channel = get_channel(
+            &mock.data_client(),
+            LookupType::Handle("@TeamYouTube".to_string()),
+        ).await.unwrap();
+        assert_eq!(channel.user_id, "UCK8sQmJBp8GCxrOtXWBpyEA");
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[("id", "UCdoesnotexist")], 200, fixture("data_api/channel_empty.json")).await;
+
+        let result = get_channel(
+            &mock.data_client(),
+            LookupType::ChannelID("UCdoesnotexist".to_string()),
         ).await;
 
-        assert!(result.is_ok());
+        assert!(matches!(result, Err(YouTubeError::NotFound)));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_get_channels_chunks_requests() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 200, fixture("data_api/channels_batch.json")).await;
+
+        let ids: Vec<String> = (0..120).map(|i| format!("UC{}", i)).collect();
+        let channels = get_channels(&mock.data_client(), &ids).await.unwrap();
+
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 3);
+        assert_eq!(channels.len(), 6);
+        assert_eq!(channels[1].user_id, "UCK8sQmJBp8GCxrOtXWBpyEA");
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_quota_exceeded() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 403, fixture("data_api/error_quota.json")).await;
+
+        let result = get_channel(
+            &mock.data_client(),
+            LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
+        ).await;
+
+        assert!(matches!(result, Err(YouTubeError::Ratelimited)));
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_server_error() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 503, fixture("data_api/error_backend.json")).await;
+
+        let result = get_channel(
+            &mock.data_client(),
+            LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
+        ).await;
+
+        assert!(matches!(result, Err(YouTubeError::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_malformed_body() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 200, "{\"items\": [{\"snippet\": {}}".to_string()).await;
+
+        let result = get_channel(
+            &mock.data_client(),
+            LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string()),
+        ).await;
+
+        assert!(matches!(result, Err(YouTubeError::ParseError(_))));
+    }
+}
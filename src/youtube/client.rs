@@ -0,0 +1,332 @@
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use crate::errors::YouTubeError;
+use crate::retry::is_retryable;
+use crate::telemetry;
+use crate::transport::Transport;
+use super::keys::{quota_cost, KeyPool};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{field, info, info_span, warn, Instrument, Span};
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    message: Option<String>,
+    errors: Option<Vec<ErrorDetail>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    reason: Option<String>,
+}
+
+/// Thin wrapper around the YouTube Data API v3. Every endpoint goes through
+/// `get` so that authentication, field masks and error mapping live in one place.
+#[derive(Debug, Clone)]
+pub struct YouTubeDataClient {
+    transport: Transport,
+    base_url: String,
+    keys: Arc<KeyPool>,
+}
+
+impl YouTubeDataClient {
+    pub fn new(transport: Transport, base_url: impl Into<String>, keys: Arc<KeyPool>) -> Self {
+        Self {
+            transport,
+            base_url: base_url.into(),
+            keys,
+        }
+    }
+
+    pub fn keys(&self) -> &KeyPool {
+        &self.keys
+    }
+
+    /// Runs inside an `upstream` span carrying the endpoint, the status of
+    /// the last attempt, the total latency and the quota units charged.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        resource: &str,
+        query: &[(&str, &str)],
+        field_mask: &str,
+    ) -> Result<T, YouTubeError> {
+        let span = info_span!(
+            "upstream",
+            api = "data_api",
+            endpoint = resource,
+            key = field::Empty,
+            status = field::Empty,
+            quota_cost = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let started = Instant::now();
+        let result = self.send(resource, query, field_mask).instrument(span.clone()).await;
+
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        telemetry::record_upstream("data_api", resource, started, &result);
+        match &result {
+            Ok(_) => info!(parent: &span, "Data API call finished"),
+            Err(e) => info!(parent: &span, error = %e, "Data API call failed"),
+        }
+        result
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        resource: &str,
+        query: &[(&str, &str)],
+        field_mask: &str,
+    ) -> Result<T, YouTubeError> {
+        let cost = quota_cost(resource);
+        let policy = self.transport.retry_policy(resource);
+        let span = Span::current();
+        let mut charged = 0;
+        let mut attempt = 1;
+
+        loop {
+            let (key_index, api_key) = self.keys.acquire(cost).ok_or(YouTubeError::Ratelimited)?;
+            span.record("key", key_index);
+
+            let request = self.transport
+                .client()
+                .get(format!("{}/{}", self.base_url, resource))
+                .query(query)
+                .header("X-Goog-Api-Key", api_key)
+                .header("X-Goog-Fieldmask", field_mask)
+                .build()
+                .map_err(|e| YouTubeError::Other(Box::new(e)))?;
+
+            let resp = match self.transport.send(request).await {
+                Ok(resp) => resp,
+                Err(e) => match policy.delay(attempt, None).filter(|_| is_retryable(&e)) {
+                    Some(delay) => {
+                        warn!(attempt, ?delay, error = %e, "Retrying Data API call");
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    None => return Err(e),
+                },
+            };
+            self.keys.record(key_index, cost);
+            charged += cost;
+            span.record("quota_cost", charged);
+            span.record("status", resp.status.as_u16());
+
+            if resp.status == StatusCode::OK {
+                return resp.json();
+            }
+
+            let body = resp.text();
+            if is_quota_exhausted(&body) {
+                warn!(key = key_index, "API key ran out of quota, rotating to the next key");
+                self.keys.exhaust(key_index);
+                continue;
+            }
+
+            let err = map_error(resp.status, &body);
+            if let Some(delay) = policy.delay(attempt, Some(&resp.headers)).filter(|_| is_retryable(&err)) {
+                warn!(attempt, ?delay, error = %err, "Retrying Data API call");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            return Err(err);
+        }
+    }
+}
+
+fn error_reasons(error: Option<&ErrorBody>) -> Vec<&str> {
+    error
+        .and_then(|e| e.errors.as_ref())
+        .map(|errors| errors.iter().filter_map(|e| e.reason.as_deref()).collect())
+        .unwrap_or_default()
+}
+
+/// Whether the key used for the request has run out of daily quota, as opposed
+/// to a short-lived rate limit that another key would hit just the same.
+fn is_quota_exhausted(body: &str) -> bool {
+    let error = serde_json::from_str::<ErrorResponse>(body).ok().map(|r| r.error);
+    error_reasons(error.as_ref())
+        .iter()
+        .any(|reason| matches!(*reason, "quotaExceeded" | "dailyLimitExceeded"))
+}
+
+fn map_error(status: StatusCode, body: &str) -> YouTubeError {
+    let error = serde_json::from_str::<ErrorResponse>(body).ok().map(|r| r.error);
+    let reasons = error_reasons(error.as_ref());
+
+    if let Some(err) = reasons.iter().find_map(|reason| error_from_reason(reason)) {
+        return err;
+    }
+
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => YouTubeError::Ratelimited,
+        StatusCode::FORBIDDEN => {
+            warn!(
+                ?reasons,
+                message = error.as_ref().and_then(|e| e.message.as_deref()).unwrap_or_default(),
+                "Unknown forbidden error",
+            );
+            YouTubeError::Forbidden
+        }
+        StatusCode::NOT_FOUND => YouTubeError::NotFound,
+        StatusCode::UNAUTHORIZED => YouTubeError::Unauthorized,
+        StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+            YouTubeError::InternalServerError
+        }
+        status => {
+            warn!(status = status.as_u16(), body, "Unknown status code");
+            YouTubeError::UnknownStatusCode(status)
+        }
+    }
+}
+
+/// Maps the structured `error.errors[].reason` codes returned by the Data API.
+/// See https://developers.google.com/youtube/v3/docs/errors
+fn error_from_reason(reason: &str) -> Option<YouTubeError> {
+    match reason {
+        "quotaExceeded" | "dailyLimitExceeded" | "rateLimitExceeded" | "userRateLimitExceeded" => {
+            Some(YouTubeError::Ratelimited)
+        }
+        "accountClosed" => Some(YouTubeError::AccountClosed),
+        "accountSuspended" => Some(YouTubeError::AccountTerminated),
+        "subscriptionForbidden" => Some(YouTubeError::SubscriptionsPrivate),
+        "channelNotFound" | "playlistNotFound" | "videoNotFound" | "subscriberNotFound" => {
+            Some(YouTubeError::NotFound)
+        }
+        "keyInvalid" | "authError" => Some(YouTubeError::Unauthorized),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{fixture, MockYouTube};
+    use crate::transport::TransportMode;
+    use crate::youtube::channels::{get_channel, LookupType};
+
+    fn error_body(reason: &str, message: &str) -> String {
+        serde_json::json!({
+            "error": {
+                "code": 403,
+                "message": message,
+                "errors": [{ "message": message, "domain": "youtube.quota", "reason": reason }]
+            }
+        }).to_string()
+    }
+
+    #[test]
+    fn test_map_error_reasons() {
+        let quota = error_body("quotaExceeded", "The request cannot be completed because you have exceeded your quota.");
+        assert!(matches!(map_error(StatusCode::FORBIDDEN, &quota), YouTubeError::Ratelimited));
+
+        let private = error_body("subscriptionForbidden", "The requester is not allowed to access the requested subscriptions.");
+        assert!(matches!(map_error(StatusCode::FORBIDDEN, &private), YouTubeError::SubscriptionsPrivate));
+
+        let closed = error_body("accountClosed", "Subscriptions could not be retrieved because the subscriber's account is closed.");
+        assert!(matches!(map_error(StatusCode::FORBIDDEN, &closed), YouTubeError::AccountClosed));
+
+        let suspended = error_body("accountSuspended", "Subscriptions could not be retrieved because the subscriber's account is suspended.");
+        assert!(matches!(map_error(StatusCode::FORBIDDEN, &suspended), YouTubeError::AccountTerminated));
+    }
+
+    #[test]
+    fn test_is_quota_exhausted() {
+        assert!(is_quota_exhausted(&error_body("quotaExceeded", "")));
+        assert!(!is_quota_exhausted(&error_body("rateLimitExceeded", "")));
+        assert!(!is_quota_exhausted("<html>"));
+    }
+
+    #[tokio::test]
+    async fn test_rotates_key_when_quota_runs_out() {
+        let mock = MockYouTube::start().await;
+        mock.data_api_for_key("exhausted-key", "channels", 403, fixture("data_api/error_quota.json")).await;
+        mock.data_api_for_key("fresh-key", "channels", 200, fixture("data_api/channel_youtube.json")).await;
+
+        let keys = Arc::new(KeyPool::new(vec!["exhausted-key".to_string(), "fresh-key".to_string()], 10_000));
+        let client = YouTubeDataClient::new(
+            Transport::new(reqwest::Client::new(), TransportMode::Live),
+            mock.config().data_api_url,
+            keys.clone(),
+        );
+
+        for _ in 0..2 {
+            let channel = get_channel(&client, LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string())).await;
+            assert!(channel.is_ok());
+        }
+
+        // The exhausted key is only tried once
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 3);
+        assert_eq!(keys.acquire(1).unwrap().1, "fresh-key");
+    }
+
+    #[tokio::test]
+    async fn test_ratelimited_once_every_key_is_exhausted() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 403, fixture("data_api/error_quota.json")).await;
+
+        let client = mock.data_client();
+        for _ in 0..2 {
+            let result = get_channel(&client, LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string())).await;
+            assert!(matches!(result, Err(YouTubeError::Ratelimited)));
+        }
+
+        // The second lookup never reaches YouTube
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_server_errors() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/channels", &[
+            (503, fixture("data_api/error_backend.json")),
+            (500, fixture("data_api/error_backend.json")),
+            (200, fixture("data_api/channel_youtube.json")),
+        ]).await;
+
+        let channel = get_channel(&mock.data_client(), LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string())).await;
+
+        assert!(channel.is_ok());
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("channels", &[], 503, fixture("data_api/error_backend.json")).await;
+
+        let result = get_channel(&mock.data_client(), LookupType::ChannelID("UCBR8-60-B28hp2BmDPdntcQ".to_string())).await;
+
+        assert!(matches!(result, Err(YouTubeError::InternalServerError)));
+        assert_eq!(mock.request_count("/youtube/v3/channels").await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_final_errors() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[], 403, fixture("data_api/error_account_suspended.json")).await;
+
+        let result = crate::youtube::subscriptions::get_subscriptions(&mock.data_client(), "UCterminated", None, 1).await;
+
+        assert!(matches!(result, Err(YouTubeError::AccountTerminated)));
+        assert_eq!(mock.request_count("/youtube/v3/subscriptions").await, 1);
+    }
+
+    #[test]
+    fn test_map_error_falls_back_to_status() {
+        let unknown = error_body("somethingNew", "Something new");
+        assert!(matches!(map_error(StatusCode::FORBIDDEN, &unknown), YouTubeError::Forbidden));
+        assert!(matches!(map_error(StatusCode::NOT_FOUND, ""), YouTubeError::NotFound));
+        assert!(matches!(map_error(StatusCode::SERVICE_UNAVAILABLE, "<html>"), YouTubeError::InternalServerError));
+        assert!(matches!(map_error(StatusCode::IM_A_TEAPOT, ""), YouTubeError::UnknownStatusCode(StatusCode::IM_A_TEAPOT)));
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{NaiveDate, Utc};
+use chrono_tz::America::Los_Angeles;
+use parking_lot::Mutex;
+
+/// Default daily quota of a Data API project
+pub const DEFAULT_DAILY_QUOTA: u64 = 10_000;
+
+/// Quota cost of a single request to `resource`.
+/// See https://developers.google.com/youtube/v3/determine_quota_cost
+pub fn quota_cost(resource: &str) -> u64 {
+    match resource {
+        "search" => 100,
+        _ => 1,
+    }
+}
+
+/// Data API quotas reset at midnight Pacific time.
+fn quota_day() -> NaiveDate {
+    Utc::now().with_timezone(&Los_Angeles).date_naive()
+}
+
+/// Estimated quota spent by one key since the last reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub used: u64,
+    pub remaining: u64,
+}
+
+#[derive(Debug)]
+struct KeyState {
+    key: String,
+    day: NaiveDate,
+    used: u64,
+}
+
+/// A set of Data API keys with a rough per-day tally of the quota each one has
+/// spent. Requests stick to the first key with budget left and move on once
+/// YouTube reports its quota as exhausted.
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Mutex<Vec<KeyState>>,
+    daily_quota: u64,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<String>, daily_quota: u64) -> Self {
+        let today = quota_day();
+        Self {
+            keys: Mutex::new(
+                keys.into_iter()
+                    .map(|key| KeyState { key, day: today, used: 0 })
+                    .collect(),
+            ),
+            daily_quota,
+        }
+    }
+
+    /// Returns the index and value of a key that can afford `cost` units today
+    pub fn acquire(&self, cost: u64) -> Option<(usize, String)> {
+        self.acquire_on(quota_day(), cost)
+    }
+
+    /// Charges `cost` units to the key at `index`
+    pub fn record(&self, index: usize, cost: u64) {
+        self.record_on(quota_day(), index, cost)
+    }
+
+    /// Marks the key at `index` as having no quota left until the next reset
+    pub fn exhaust(&self, index: usize) {
+        self.exhaust_on(quota_day(), index)
+    }
+
+    /// Today's usage of every key, in the order they were given
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        self.usage_on(quota_day())
+    }
+
+    fn acquire_on(&self, today: NaiveDate, cost: u64) -> Option<(usize, String)> {
+        let mut keys = self.keys.lock();
+        keys.iter_mut()
+            .enumerate()
+            .find_map(|(index, state)| {
+                reset_if_stale(state, today);
+                (state.used + cost <= self.daily_quota).then(|| (index, state.key.clone()))
+            })
+    }
+
+    fn record_on(&self, today: NaiveDate, index: usize, cost: u64) {
+        if let Some(state) = self.keys.lock().get_mut(index) {
+            reset_if_stale(state, today);
+            state.used += cost;
+        }
+    }
+
+    fn exhaust_on(&self, today: NaiveDate, index: usize) {
+        if let Some(state) = self.keys.lock().get_mut(index) {
+            reset_if_stale(state, today);
+            state.used = state.used.max(self.daily_quota);
+        }
+    }
+
+    fn usage_on(&self, today: NaiveDate) -> Vec<KeyUsage> {
+        let mut keys = self.keys.lock();
+        keys.iter_mut()
+            .map(|state| {
+                reset_if_stale(state, today);
+                KeyUsage {
+                    used: state.used,
+                    remaining: self.daily_quota.saturating_sub(state.used),
+                }
+            })
+            .collect()
+    }
+}
+
+fn reset_if_stale(state: &mut KeyState, today: NaiveDate) {
+    if state.day != today {
+        state.day = today;
+        state.used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, d).unwrap()
+    }
+
+    #[test]
+    fn test_acquire_spends_keys_in_order() {
+        let pool = KeyPool::new(vec!["key-one".to_string(), "key-two".to_string()], 3);
+
+        for _ in 0..3 {
+            let (index, key) = pool.acquire_on(day(1), 1).unwrap();
+            assert_eq!((index, key.as_str()), (0, "key-one"));
+            pool.record_on(day(1), index, 1);
+        }
+
+        assert_eq!(pool.acquire_on(day(1), 1).unwrap().1, "key-two");
+        assert!(pool.acquire_on(day(1), 100).is_none());
+    }
+
+    #[test]
+    fn test_exhausted_key_is_skipped_until_reset() {
+        let pool = KeyPool::new(vec!["key-one".to_string(), "key-two".to_string()], 10_000);
+        pool.exhaust_on(day(1), 0);
+        assert_eq!(pool.acquire_on(day(1), 1).unwrap().0, 1);
+
+        pool.exhaust_on(day(1), 1);
+        assert!(pool.acquire_on(day(1), 1).is_none());
+
+        // Quotas reset on the next Pacific day
+        assert_eq!(pool.acquire_on(day(2), 1).unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_usage() {
+        let pool = KeyPool::new(vec!["key-one".to_string(), "key-two".to_string()], 100);
+        pool.record_on(day(1), 0, 30);
+        pool.exhaust_on(day(1), 1);
+
+        assert_eq!(pool.usage_on(day(1)), [
+            KeyUsage { used: 30, remaining: 70 },
+            KeyUsage { used: 100, remaining: 0 },
+        ]);
+        assert_eq!(pool.usage_on(day(2))[0], KeyUsage { used: 0, remaining: 100 });
+    }
+}
@@ -1,4 +1,8 @@
+pub mod client;
+pub mod keys;
+pub mod pages;
 pub mod channels;
 pub mod videos;
 pub mod subscriptions;
-pub mod playlist_items;
\ No newline at end of file
+pub mod playlist_items;
+pub mod playlists;
\ No newline at end of file
@@ -0,0 +1,94 @@
+use std::future::Future;
+use futures::stream::{self, Stream, TryStreamExt};
+use crate::errors::YouTubeError;
+
+/// Why a crawl stopped before the last page, and where to pick it up again
+#[derive(Debug)]
+pub struct CrawlError {
+    pub error: YouTubeError,
+    /// Token of the page that could not be fetched
+    pub page_token: Option<String>,
+}
+
+/// One item of a crawl
+#[derive(Debug)]
+pub struct Crawled<T> {
+    pub item: T,
+    /// Whether this is the last item of the last page, so a crawl that stops
+    /// here has seen everything
+    pub last: bool,
+}
+
+/// Flattens the pages returned by `fetch` into a stream of items, following
+/// next page tokens until there are none. Each page is only fetched once the
+/// previous one has been consumed, and the stream ends after an error.
+pub fn paginate<T, F, Fut>(page_token: Option<String>, fetch: F) -> impl Stream<Item = Result<Crawled<T>, CrawlError>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>), YouTubeError>>,
+{
+    // `None` once the last page has been fetched
+    let next: Option<Option<String>> = Some(page_token);
+
+    stream::try_unfold((next, fetch), |(next, mut fetch)| async move {
+        let Some(page_token) = next else {
+            return Ok(None);
+        };
+        match fetch(page_token.clone()).await {
+            Ok((items, next_page_token)) => {
+                let last_page = next_page_token.is_none();
+                Ok(Some(((items, last_page), (next_page_token.map(Some), fetch))))
+            }
+            Err(error) => Err(CrawlError { error, page_token }),
+        }
+    })
+    .map_ok(|(items, last_page)| {
+        let count = items.len();
+        stream::iter(items.into_iter().enumerate().map(move |(i, item)| {
+            Ok(Crawled { item, last: last_page && i + 1 == count })
+        }))
+    })
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_paginate_follows_page_tokens() {
+        let items: Vec<Crawled<u32>> = paginate(None, |page_token| async move {
+            Ok(match page_token.as_deref() {
+                None => (vec![1, 2], Some("page2".to_string())),
+                Some("page2") => (vec![3, 4], None),
+                _ => unreachable!("there is no third page"),
+            })
+        })
+        .try_collect()
+        .await
+        .unwrap();
+
+        let items: Vec<(u32, bool)> = items.into_iter().map(|crawled| (crawled.item, crawled.last)).collect();
+        assert_eq!(items, [(1, false), (2, false), (3, false), (4, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_stops_after_an_error() {
+        let items: Vec<Result<Crawled<u32>, CrawlError>> = paginate(None, |page_token| async move {
+            match page_token {
+                None => Ok((vec![1], Some("page2".to_string()))),
+                Some(_) => Err(YouTubeError::Ratelimited),
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(items.len(), 2);
+        let Err(error) = &items[1] else {
+            panic!("expected the second page to fail");
+        };
+        assert!(matches!(error.error, YouTubeError::Ratelimited));
+        assert_eq!(error.page_token.as_deref(), Some("page2"));
+    }
+}
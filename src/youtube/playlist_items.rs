@@ -1,8 +1,11 @@
-use reqwest::Client;
 use serde::Deserialize;
 use chrono::DateTime;
+use futures::stream::Stream;
 use crate::models::Video;
 use crate::errors::YouTubeError;
+use super::client::YouTubeDataClient;
+use super::pages::{paginate, CrawlError, Crawled};
+use super::videos::populate_video_stats;
 
 #[derive(Debug, Deserialize)]
 struct ApiResponse {
@@ -32,78 +35,30 @@ struct ResourceId {
     video_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct ErrorResponse {
-    error: Error,
-}
-
-#[derive(Debug, Deserialize)]
-struct Error {
-    message: String,
-}
-
 pub async fn get_playlist_items(
-    client: &Client,
+    client: &YouTubeDataClient,
     playlist_id: &str,
-    api_key: &str,
     page_token: Option<&str>,
     max_results: u32,
 ) -> Result<(Vec<Video>, Option<String>), YouTubeError> {
-    let mut url = format!(
-        "https://youtube.googleapis.com/youtube/v3/playlistItems?playlistId={}&part=snippet&maxResults={}",
-        playlist_id,
-        max_results
-    );
+    let max_results = max_results.to_string();
+    let mut query = vec![
+        ("playlistId", playlist_id),
+        ("part", "snippet"),
+        ("maxResults", max_results.as_str()),
+    ];
 
     if let Some(token) = page_token {
-        url.push_str(&format!("&pageToken={}", token));
+        query.push(("pageToken", token));
     }
 
-    let mut request = client
-        .get(&url)
-        .header("Host", "youtube.googleapis.com")
-        .header("X-Goog-Fieldmask", "nextPageToken,items(snippet(publishedAt,title,description,resourceId.videoId))");
-
-    request = request.header("X-Goog-Api-Key", api_key);
-
-    let resp = request
-        .send()
-        .await
-        .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-    match resp.status() {
-        reqwest::StatusCode::TOO_MANY_REQUESTS => return Err(YouTubeError::Ratelimited),
-        reqwest::StatusCode::FORBIDDEN => {
-            let error_response: ErrorResponse = resp
-                .json()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            
-            if error_response.error.message.starts_with("The request cannot be completed because you have exceeded your") {
-                return Err(YouTubeError::Ratelimited);
-            }
-            return Err(YouTubeError::Forbidden);
-        },
-        reqwest::StatusCode::NOT_FOUND => return Err(YouTubeError::NotFound),
-        reqwest::StatusCode::UNAUTHORIZED => return Err(YouTubeError::Unauthorized),
-        reqwest::StatusCode::INTERNAL_SERVER_ERROR | reqwest::StatusCode::SERVICE_UNAVAILABLE => {
-            return Err(YouTubeError::InternalServerError);
-        },
-        reqwest::StatusCode::OK => (), // Continue processing
-        status => {
-            let body = resp
-                .text()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            eprintln!("Unknown status code {}: {}", status.as_u16(), body);
-            return Err(YouTubeError::UnknownStatusCode(status));
-        }
-    }
-
-    let api_response: ApiResponse = resp
-        .json()
-        .await
-        .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+    let api_response: ApiResponse = client
+        .get(
+            "playlistItems",
+            &query,
+            "nextPageToken,items(snippet(publishedAt,title,description,resourceId.videoId))",
+        )
+        .await?;
 
     let videos = api_response.items
         .unwrap_or_default()
@@ -135,40 +90,64 @@ pub async fn get_playlist_items(
     Ok((videos, api_response.next_page_token))
 }
 
+/// Every video of `playlist_id` from `page_token` on with its stats filled
+/// in, fetching the next page only once the previous one has been consumed
+pub fn stream_playlist_items(
+    client: YouTubeDataClient,
+    playlist_id: String,
+    page_token: Option<String>,
+    max_results: u32,
+) -> impl Stream<Item = Result<Crawled<Video>, CrawlError>> + Send + 'static {
+    paginate(page_token, move |page_token| {
+        let (client, playlist_id) = (client.clone(), playlist_id.clone());
+        async move {
+            let (mut videos, next_page_token) = get_playlist_items(&client, &playlist_id, page_token.as_deref(), max_results).await?;
+            populate_video_stats(&client, &mut videos).await?;
+            Ok((videos, next_page_token))
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    
-    fn get_api_key() -> String {
-        dotenvy::dotenv().ok();
-        env::var("API_KEY").expect("API_KEY must be set")
-    }
+    use crate::test_support::{fixture, MockYouTube};
 
     #[tokio::test]
     async fn test_get_playlist_items() {
-        let client = Client::new();
-        let result = get_playlist_items(
-            &client,
+        let mock = MockYouTube::start().await;
+        mock.data_api(
+            "playlistItems",
+            &[("playlistId", "UUwBkSWEuckW8AHZ62XcSLYw"), ("maxResults", "5")],
+            200,
+            fixture("data_api/playlist_items.json"),
+        ).await;
+
+        let (videos, next_page_token) = get_playlist_items(
+            &mock.data_client(),
             "UUwBkSWEuckW8AHZ62XcSLYw",
-            &get_api_key(),
             None,
             5,
-        ).await;
+        ).await.unwrap();
+
+        // The entry without a video ID is dropped
+        assert_eq!(videos.len(), 2);
+        assert_eq!(next_page_token.as_deref(), Some("EAAaBlBUOkNBVQ"));
+
+        let first_video = &videos[0];
+        assert_eq!(first_video.video_id, "gfKpRpwHckY");
+        assert_eq!(first_video.title, "First Video!!!");
+        assert_eq!(first_video.description, "");
+        // 2023-07-08T13:29:24Z as timestamp
+        assert_eq!(first_video.created_at, 1688822964);
+    }
 
-        match result {
-            Ok((videos, next_page_token)) => {
-                assert_eq!(videos.len(), 5);
-                assert!(next_page_token.is_some());
-
-                let first_video = &videos[0];
-                assert_eq!(first_video.video_id, "gfKpRpwHckY");
-                assert_eq!(first_video.title, "First Video!!!");
-                assert_eq!(first_video.description, "");
-                // 2023-07-08T13:29:24Z as timestamp
-                assert_eq!(first_video.created_at, 1688822964);
-            }
-            Err(e) => panic!("Expected successful response, got error: {:?}", e),
-        }
+    #[tokio::test]
+    async fn test_get_playlist_items_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlistItems", &[], 404, crate::test_support::not_found()).await;
+
+        let result = get_playlist_items(&mock.data_client(), "UUmissing", None, 5).await;
+        assert!(matches!(result, Err(YouTubeError::NotFound)));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use chrono::DateTime;
+use crate::models::PlaylistSummary;
+use crate::errors::YouTubeError;
+use super::client::YouTubeDataClient;
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    items: Option<Vec<ApiPlaylist>>
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiPlaylist {
+    id: String,
+    snippet: PlaylistSnippet,
+    #[serde(rename = "contentDetails")]
+    content_details: Option<ContentDetails>
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistSnippet {
+    #[serde(rename = "channelId")]
+    channel_id: String,
+    title: Option<String>,
+    #[serde(rename = "publishedAt")]
+    published_at: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentDetails {
+    #[serde(rename = "itemCount")]
+    item_count: Option<i64>
+}
+
+/// Title, creation date, size and owner of a single playlist
+pub async fn get_playlist(
+    client: &YouTubeDataClient,
+    playlist_id: &str,
+) -> Result<PlaylistSummary, YouTubeError> {
+    let api_response: ApiResponse = client
+        .get(
+            "playlists",
+            &[("id", playlist_id), ("part", "contentDetails,snippet")],
+            "items(id,snippet(channelId,title,publishedAt),contentDetails.itemCount)",
+        )
+        .await?;
+
+    let playlist = api_response.items
+        .and_then(|mut items| items.pop())
+        .ok_or(YouTubeError::NotFound)?;
+
+    Ok(PlaylistSummary {
+        playlist_id: playlist.id,
+        channel_id: playlist.snippet.channel_id,
+        title: playlist.snippet.title.unwrap_or_default(),
+        created_at: playlist.snippet.published_at
+            .and_then(|dt| DateTime::parse_from_rfc3339(&dt).ok())
+            .map(|dt| dt.timestamp())
+            .unwrap_or_default(),
+        video_count: playlist.content_details.and_then(|c| c.item_count),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{fixture, MockYouTube};
+
+    #[tokio::test]
+    async fn test_get_playlist() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlists", &[("id", "PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb")], 200, fixture("data_api/playlist_snippet.json")).await;
+
+        let playlist = get_playlist(&mock.data_client(), "PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb").await.unwrap();
+        assert_eq!(playlist.playlist_id, "PLbpi6ZahtOH6Blw3RGYpWkSByi_T7Rygb");
+        assert_eq!(playlist.channel_id, "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(playlist.title, "YouTube Originals");
+        assert_eq!(playlist.created_at, 1429722161);
+        assert_eq!(playlist.video_count, Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_get_playlist_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("playlists", &[], 200, fixture("data_api/channel_empty.json")).await;
+
+        let result = get_playlist(&mock.data_client(), "PLdeleted").await;
+        assert!(matches!(result, Err(YouTubeError::NotFound)));
+    }
+}
@@ -1,8 +1,10 @@
-use reqwest::Client;
 use serde::Deserialize;
 use chrono::DateTime;
+use futures::stream::Stream;
 use crate::models::Subscription;
 use crate::errors::YouTubeError;
+use super::client::YouTubeDataClient;
+use super::pages::{paginate, CrawlError, Crawled};
 
 #[derive(Debug, Deserialize)]
 struct ApiResponse {
@@ -42,92 +44,31 @@ struct Thumbnail {
     url: Option<String>
 }
 
-#[derive(Debug, Deserialize)]
-struct ErrorResponse {
-    error: Error,
-}
-
-#[derive(Debug, Deserialize)]
-struct Error {
-    message: String,
-}
-
 pub async fn get_subscriptions(
-    client: &Client,
+    client: &YouTubeDataClient,
     channel_id: &str,
-    api_key: &str,
     page_token: Option<&str>,
     max_results: u32,
 ) -> Result<(Vec<Subscription>, Option<String>), YouTubeError> {
-    let mut url = format!(
-        "https://youtube.googleapis.com/youtube/v3/subscriptions?channelId={}&part=snippet&order=alphabetical&maxResults={}",
-        channel_id,
-        max_results
-    );
+    let max_results = max_results.to_string();
+    let mut query = vec![
+        ("channelId", channel_id),
+        ("part", "snippet"),
+        ("order", "alphabetical"),
+        ("maxResults", max_results.as_str()),
+    ];
 
     if let Some(token) = page_token {
-        url.push_str(&format!("&pageToken={}", token));
-    }
-
-    let mut request = client
-        .get(&url)
-        .header("Host", "youtube.googleapis.com")
-        .header("X-Goog-Fieldmask", "nextPageToken,items(snippet(publishedAt,title,resourceId.channelId,thumbnails.default.url))");
-
-    request = request.header("X-Goog-Api-Key", api_key);
-
-    let resp = request
-        .send()
-        .await
-        .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-    match resp.status() {
-        reqwest::StatusCode::TOO_MANY_REQUESTS => return Err(YouTubeError::Ratelimited),
-        reqwest::StatusCode::FORBIDDEN => {
-            let error_response: ErrorResponse = resp
-                .json()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            
-            match error_response.error.message.as_str() {
-                "Subscriptions could not be retrieved because the subscriber's account is closed." => {
-                    return Err(YouTubeError::AccountClosed)
-                },
-                "Subscriptions could not be retrieved because the subscriber's account is suspended." => {
-                    return Err(YouTubeError::AccountTerminated)
-                },
-                "The requester is not allowed to access the requested subscriptions." => {
-                    return Err(YouTubeError::SubscriptionsPrivate)
-                },
-                msg if msg.starts_with("The request cannot be completed because you have exceeded your") => {
-                    return Err(YouTubeError::Ratelimited)
-                },
-                _ => {
-                    eprintln!("Unknown forbidden error message: {}", error_response.error.message);
-                    return Err(YouTubeError::Forbidden)
-                }
-            }
-        },
-        reqwest::StatusCode::NOT_FOUND => return Err(YouTubeError::NotFound),
-        reqwest::StatusCode::UNAUTHORIZED => return Err(YouTubeError::Unauthorized),
-        reqwest::StatusCode::INTERNAL_SERVER_ERROR | reqwest::StatusCode::SERVICE_UNAVAILABLE => {
-            return Err(YouTubeError::InternalServerError);
-        },
-        reqwest::StatusCode::OK => (), // Continue processing
-        status => {
-            let body = resp
-                .text()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            eprintln!("Unknown status code {}: {}", status.as_u16(), body);
-            return Err(YouTubeError::UnknownStatusCode(status));
-        }
+        query.push(("pageToken", token));
     }
 
-    let api_response: ApiResponse = resp
-        .json()
-        .await
-        .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+    let api_response: ApiResponse = client
+        .get(
+            "subscriptions",
+            &query,
+            "nextPageToken,items(snippet(publishedAt,title,resourceId.channelId,thumbnails.default.url))",
+        )
+        .await?;
 
     let subscriptions = api_response.items
         .unwrap_or_default()
@@ -168,39 +109,111 @@ pub async fn get_subscriptions(
     Ok((subscriptions, api_response.next_page_token))
 }
 
+/// Every subscription of `channel_id` from `page_token` on, fetching the
+/// next page only once the previous one has been consumed
+pub fn stream_subscriptions(
+    client: YouTubeDataClient,
+    channel_id: String,
+    page_token: Option<String>,
+    max_results: u32,
+) -> impl Stream<Item = Result<Crawled<Subscription>, CrawlError>> + Send + 'static {
+    paginate(page_token, move |page_token| {
+        let (client, channel_id) = (client.clone(), channel_id.clone());
+        async move {
+            get_subscriptions(&client, &channel_id, page_token.as_deref(), max_results).await
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    
-    fn get_api_key() -> String {
-        dotenvy::dotenv().ok();
-        env::var("API_KEY").expect("API_KEY must be set")
-    }
+    use futures::StreamExt;
+    use crate::test_support::{fixture, MockYouTube};
 
     #[tokio::test]
     async fn test_get_subscriptions() {
-        let client = Client::new();
-        let result = get_subscriptions(
-            &client,
+        let mock = MockYouTube::start().await;
+        mock.data_api(
+            "subscriptions",
+            &[("channelId", "UCewMTclBJZPaNEfbf-qYMGA"), ("maxResults", "5"), ("order", "alphabetical")],
+            200,
+            fixture("data_api/subscriptions.json"),
+        ).await;
+
+        let (subscriptions, next_page_token) = get_subscriptions(
+            &mock.data_client(),
             "UCewMTclBJZPaNEfbf-qYMGA",
-            &get_api_key(),
             None,
             5,
-        ).await;
+        ).await.unwrap();
+
+        // The entry without a channel ID is dropped
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(next_page_token, Some("CAUQAA".to_string()));
+
+        let sub = &subscriptions[0];
+        assert_eq!(sub.channel_id, "UCewMTclBJZPaNEfbf-qYMGA");
+        assert_eq!(sub.title, "unstopble gameing");
+        // 2024-03-04T10:27:40.003572Z as timestamp
+        assert_eq!(sub.created_at, 1709548060);
+        assert_eq!(sub.profile_picture.as_deref(), Some("sub_one"));
+    }
 
-        match result {
-            Ok((subscriptions, next_page_token)) => {
-                assert_eq!(subscriptions.len(), 5);
-                assert_eq!(next_page_token, Some("CAUQAA".to_string()));
-
-                let sub = &subscriptions[0];
-                assert_eq!(sub.channel_id, "UCewMTclBJZPaNEfbf-qYMGA");
-                assert_eq!(sub.title, "unstopble gameing");
-                // 2024-03-04T10:27:40.003572Z as timestamp
-                assert_eq!(sub.created_at, 1709548060);
-            }
-            Err(e) => panic!("Expected successful response, got error: {:?}", e),
-        }
+    #[tokio::test]
+    async fn test_get_subscriptions_page_token() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[("pageToken", "CAUQAA")], 200, fixture("data_api/subscriptions.json")).await;
+
+        let result = get_subscriptions(&mock.data_client(), "UCewMTclBJZPaNEfbf-qYMGA", Some("CAUQAA"), 5).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stream_subscriptions_reports_failed_page() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtube/v3/subscriptions", &[
+            (200, fixture("data_api/subscriptions.json")),
+            (403, fixture("data_api/error_quota.json")),
+        ]).await;
+
+        let results: Vec<_> = stream_subscriptions(mock.data_client(), "UCewMTclBJZPaNEfbf-qYMGA".to_string(), None, 5)
+            .collect()
+            .await;
+
+        let (last, subscriptions) = results.split_last().unwrap();
+        assert!(subscriptions.iter().all(|result| result.is_ok()));
+        let Err(error) = last else {
+            panic!("expected the crawl to fail on the second page");
+        };
+        assert!(matches!(error.error, YouTubeError::Ratelimited));
+        assert_eq!(error.page_token.as_deref(), Some("CAUQAA"));
+    }
+
+    #[tokio::test]
+    async fn test_get_subscriptions_private() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[], 403, fixture("data_api/error_subscription_forbidden.json")).await;
+
+        let result = get_subscriptions(&mock.data_client(), "UCBR8-60-B28hp2BmDPdntcQ", None, 5).await;
+        assert!(matches!(result, Err(YouTubeError::SubscriptionsPrivate)));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_get_subscriptions_closed() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[], 403, fixture("data_api/error_account_closed.json")).await;
+
+        let result = get_subscriptions(&mock.data_client(), "UCclosed", None, 1).await;
+        assert!(matches!(result, Err(YouTubeError::AccountClosed)));
+    }
+
+    #[tokio::test]
+    async fn test_get_subscriptions_terminated() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("subscriptions", &[], 403, fixture("data_api/error_account_suspended.json")).await;
+
+        let result = get_subscriptions(&mock.data_client(), "UCterminated", None, 1).await;
+        assert!(matches!(result, Err(YouTubeError::AccountTerminated)));
+    }
+}
@@ -1,7 +1,8 @@
-use reqwest::Client;
 use serde::Deserialize;
-use crate::models::Video;
+use chrono::DateTime;
+use crate::models::{Video, VideoSummary};
 use crate::errors::YouTubeError;
+use super::client::YouTubeDataClient;
 
 #[derive(Debug, Deserialize)]
 struct ApiResponse {
@@ -26,6 +27,8 @@ struct Statistics {
     comment_count: Option<String>
 }
 
+// Only the presence of this object is used to flag livestreams
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct LiveStreamingDetails {
     #[serde(rename = "actualStartTime")]
@@ -35,19 +38,31 @@ struct LiveStreamingDetails {
 }
 
 #[derive(Debug, Deserialize)]
-struct ErrorResponse {
-    error: Error,
+struct SnippetResponse {
+    items: Option<Vec<SnippetVideo>>
 }
 
 #[derive(Debug, Deserialize)]
-struct Error {
-    message: String,
+struct SnippetVideo {
+    id: String,
+    snippet: VideoSnippet
+}
+
+#[derive(Debug, Deserialize)]
+struct VideoSnippet {
+    #[serde(rename = "channelId")]
+    channel_id: String,
+    title: Option<String>,
+    #[serde(rename = "publishedAt")]
+    published_at: Option<String>
 }
 
+// (livestream, views, likes, comments)
+type VideoStats = (bool, Option<i64>, Option<i64>, Option<i64>);
+
 pub async fn populate_video_stats(
-    client: &Client,
-    videos: &mut Vec<Video>,
-    api_key: &str,
+    client: &YouTubeDataClient,
+    videos: &mut [Video],
 ) -> Result<(), YouTubeError> {
     // If no videos, return early
     if videos.is_empty() {
@@ -60,58 +75,16 @@ pub async fn populate_video_stats(
     // Create chunks of 50 videos (YouTube API limit)
     for chunk in video_ids.chunks(50) {
         let ids = chunk.join(",");
-        let url = format!(
-            "https://youtube.googleapis.com/youtube/v3/videos?id={}&part=liveStreamingDetails,statistics",
-            ids
-        );
-
-        let request = client
-            .get(&url)
-            .header("Host", "youtube.googleapis.com")
-            .header("X-Goog-Api-Key", api_key)
-            .header("X-Goog-Fieldmask", "items(id,statistics(viewCount,likeCount,commentCount),liveStreamingDetails(actualStartTime,concurrentViewers))");
-
-        let resp = request
-            .send()
-            .await
-            .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-        match resp.status() {
-            reqwest::StatusCode::TOO_MANY_REQUESTS => return Err(YouTubeError::Ratelimited),
-            reqwest::StatusCode::FORBIDDEN => {
-                let error_response: ErrorResponse = resp
-                    .json()
-                    .await
-                    .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-                
-                if error_response.error.message.starts_with("The request cannot be completed because you have exceeded your") {
-                    return Err(YouTubeError::Ratelimited);
-                }
-                return Err(YouTubeError::Forbidden);
-            },
-            reqwest::StatusCode::NOT_FOUND => return Err(YouTubeError::NotFound),
-            reqwest::StatusCode::UNAUTHORIZED => return Err(YouTubeError::Unauthorized),
-            reqwest::StatusCode::INTERNAL_SERVER_ERROR | reqwest::StatusCode::SERVICE_UNAVAILABLE => {
-                return Err(YouTubeError::InternalServerError);
-            },
-            reqwest::StatusCode::OK => (), // Continue processing
-            status => {
-                let body = resp
-                    .text()
-                    .await
-                    .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-                eprintln!("Unknown status code {}: {}", status.as_u16(), body);
-                return Err(YouTubeError::UnknownStatusCode(status));
-            }
-        }
-
-        let api_response: ApiResponse = resp
-            .json()
-            .await
-            .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+        let api_response: ApiResponse = client
+            .get(
+                "videos",
+                &[("id", ids.as_str()), ("part", "liveStreamingDetails,statistics")],
+                "items(id,statistics(viewCount,likeCount,commentCount),liveStreamingDetails(actualStartTime,concurrentViewers))",
+            )
+            .await?;
 
         // Create a map of video stats
-        let stats_map: std::collections::HashMap<String, (bool, Option<i64>, Option<i64>, Option<i64>)> = 
+        let stats_map: std::collections::HashMap<String, VideoStats> =
             api_response.items
                 .unwrap_or_default()
                 .into_iter()
@@ -151,54 +124,109 @@ pub async fn populate_video_stats(
     Ok(())
 }
 
+/// Title, upload date and uploader of a single video
+pub async fn get_video(
+    client: &YouTubeDataClient,
+    video_id: &str,
+) -> Result<VideoSummary, YouTubeError> {
+    let api_response: SnippetResponse = client
+        .get("videos", &[("id", video_id), ("part", "snippet")], "items(id,snippet(channelId,title,publishedAt))")
+        .await?;
+
+    let video = api_response.items
+        .and_then(|mut items| items.pop())
+        .ok_or(YouTubeError::NotFound)?;
+
+    Ok(VideoSummary {
+        video_id: video.id,
+        channel_id: video.snippet.channel_id,
+        title: video.snippet.title.unwrap_or_default(),
+        created_at: video.snippet.published_at
+            .and_then(|dt| DateTime::parse_from_rfc3339(&dt).ok())
+            .map(|dt| dt.timestamp())
+            .unwrap_or_default(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    
-    fn get_api_key() -> String {
-        dotenvy::dotenv().ok();
-        env::var("API_KEY").expect("API_KEY must be set")
+    use crate::test_support::{fixture, MockYouTube};
+
+    fn video(video_id: &str) -> Video {
+        Video {
+            video_id: video_id.to_string(),
+            title: "".to_string(),
+            description: "".to_string(),
+            livestream: false,
+            views: None,
+            likes: None,
+            comments: None,
+            created_at: 0
+        }
     }
 
     #[tokio::test]
     async fn test_populate_video_stats() {
-        let client = Client::new();
-        let mut videos = vec![
-            Video {
-                video_id: "jfKfPfyJRdk".to_string(),
-                title: "lofi hip hop radio 📚 - beats to relax/study to".to_string(),
-                description: "".to_string(),
-                livestream: false,
-                views: None,
-                likes: None,
-                comments: None,
-                created_at: 1657641570
-            },
-            Video {
-                video_id: "SM66GDRyIVY".to_string(),
-                title: "Turning Red | Official Trailer".to_string(),
-                description: "".to_string(),
-                livestream: false,
-                views: None,
-                likes: None,
-                comments: None,
-                created_at: 1643673600
-            }
-        ];
+        let mock = MockYouTube::start().await;
+        mock.data_api("videos", &[("id", "jfKfPfyJRdk,gfKpRpwHckY")], 200, fixture("data_api/videos.json")).await;
 
-        let result = populate_video_stats(&client, &mut videos, &get_api_key()).await;
+        let mut videos = vec![video("jfKfPfyJRdk"), video("gfKpRpwHckY")];
+        let result = populate_video_stats(&mock.data_client(), &mut videos).await;
         assert!(result.is_ok());
 
         let first_video = &videos[0];
         assert!(first_video.livestream);
-        assert!(first_video.views.is_some());
-        assert!(first_video.likes.is_some());
-        
+        assert_eq!(first_video.views, Some(980000000));
+        assert_eq!(first_video.likes, Some(17000000));
+        assert_eq!(first_video.comments, None);
+
         let second_video = &videos[1];
         assert!(!second_video.livestream);
-        assert!(second_video.views.is_some());
-        assert!(second_video.likes.is_some());
-        assert!(second_video.comments.is_some());
+        assert_eq!(second_video.views, Some(1520));
+        assert_eq!(second_video.likes, Some(87));
+        assert_eq!(second_video.comments, Some(12));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_populate_video_stats_chunks_requests() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("videos", &[], 200, fixture("data_api/videos.json")).await;
+
+        let mut videos: Vec<Video> = (0..120).map(|i| video(&format!("video{}", i))).collect();
+        populate_video_stats(&mock.data_client(), &mut videos).await.unwrap();
+
+        assert_eq!(mock.request_count("/youtube/v3/videos").await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_video() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("videos", &[("id", "dQw4w9WgXcQ"), ("part", "snippet")], 200, fixture("data_api/video_snippet.json")).await;
+
+        let video = get_video(&mock.data_client(), "dQw4w9WgXcQ").await.unwrap();
+        assert_eq!(video.video_id, "dQw4w9WgXcQ");
+        assert_eq!(video.channel_id, "UCBR8-60-B28hp2BmDPdntcQ");
+        assert_eq!(video.title, "Rick Astley - Never Gonna Give You Up (Official Music Video)");
+        assert_eq!(video.created_at, 1256453853);
+    }
+
+    #[tokio::test]
+    async fn test_get_video_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.data_api("videos", &[], 200, fixture("data_api/channel_empty.json")).await;
+
+        let result = get_video(&mock.data_client(), "deleted0000").await;
+        assert!(matches!(result, Err(YouTubeError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_populate_video_stats_empty() {
+        let mock = MockYouTube::start().await;
+
+        let mut videos = Vec::new();
+        populate_video_stats(&mock.data_client(), &mut videos).await.unwrap();
+
+        assert_eq!(mock.request_count("/youtube/v3/videos").await, 0);
+    }
+}
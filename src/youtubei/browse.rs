@@ -1,9 +1,12 @@
 use serde::{Serialize, Deserialize};
-use reqwest::Client;
 use std::collections::HashSet;
+use std::time::Instant;
+use tracing::warn;
 use crate::models::Channel;
 use crate::models::VerificationStatus;
 use crate::errors::YouTubeError;
+use crate::telemetry;
+use super::client::YouTubeiClient;
 
 const ALL_COUNTRIES: &[&str] = &[
     "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
@@ -178,36 +181,44 @@ struct BrowseResponse {
     metadata: Option<Metadata>,
 }
 
+/// Fills in the fields only InnerTube knows: handle, conditional redirect,
+/// noindex, verification badge and blocked countries
 pub async fn enrich_channel_data(
-    client: &Client,
+    client: &YouTubeiClient,
+    channel: &mut Channel,
+) -> Result<(), YouTubeError> {
+    let started = Instant::now();
+    let result = browse(client, channel).await;
+    telemetry::record_upstream("innertube", "browse", started, &result);
+    result
+}
+
+async fn browse(
+    client: &YouTubeiClient,
     channel: &mut Channel,
 ) -> Result<(), YouTubeError> {
     let request = BrowseRequest {
         context: InnertubeContext {
             client: InnertubeClient {
                 client_name: "WEB".to_string(),
-                client_version: "2.20250108.06.00".to_string(),
+                client_version: client.client_version().to_string(),
             },
         },
         browse_id: channel.user_id.clone(),
     };
 
     let resp = client
-        .post("https://www.youtube.com/youtubei/v1/browse?prettyPrint=false")
-        .header("Host", "www.youtube.com")
-        .header("Content-Type", "application/json")
-        .header("X-Goog-Fieldmask", "onResponseReceivedActions.navigateAction.endpoint.browseEndpoint.browseId,header.pageHeaderRenderer.content.pageHeaderViewModel.title.dynamicTextViewModel.text.attachmentRuns.element.type.imageType.image.sources.clientResource.imageName,metadata.channelMetadataRenderer.ownerUrls,microformat.microformatDataRenderer(noindex,availableCountries)")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-    match resp.status() {
+        .post(
+            "browse?prettyPrint=false",
+            "www.youtube.com",
+            "onResponseReceivedActions.navigateAction.endpoint.browseEndpoint.browseId,header.pageHeaderRenderer.content.pageHeaderViewModel.title.dynamicTextViewModel.text.attachmentRuns.element.type.imageType.image.sources.clientResource.imageName,metadata.channelMetadataRenderer.ownerUrls,microformat.microformatDataRenderer(noindex,availableCountries)",
+            &request,
+        )
+        .await?;
+
+    match resp.status {
         reqwest::StatusCode::OK => {
-            let response: BrowseResponse = resp
-                .json()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+            let response: BrowseResponse = resp.json()?;
 
             // Handle conditional redirect
             if let Some(actions) = response.on_response_received_actions {
@@ -269,7 +280,7 @@ pub async fn enrich_channel_data(
             Ok(())
         }
         status => {
-            eprintln!("Unexpected status code: {}", status);
+            warn!(status = status.as_u16(), "Unexpected browse status code");
             Err(YouTubeError::UnknownStatusCode(status))
         }
     }
@@ -278,14 +289,12 @@ pub async fn enrich_channel_data(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use reqwest::Client;
     use crate::models::Channel;
-    
-    #[tokio::test]
-    async fn test_channel_with_blocked_us() {
-        let client = Client::new();
-        let mut channel = Channel {
-            user_id: "UC7A4ikI7Q4Efju5WNRdIqyg".to_string(),
+    use crate::test_support::{fixture, MockYouTube};
+
+    fn channel(user_id: &str) -> Channel {
+        Channel {
+            user_id: user_id.to_string(),
             display_name: None,
             description: None,
             handle: None,
@@ -304,18 +313,26 @@ mod tests {
             no_index: None,
             verification: None,
             blocked_countries: None,
-        };
+        }
+    }
 
-        let result = enrich_channel_data(&client, &mut channel).await;
+    #[tokio::test]
+    async fn test_channel_with_blocked_us() {
+        let mock = MockYouTube::start().await;
+        mock.browse("UC7A4ikI7Q4Efju5WNRdIqyg", 200, fixture("innertube/browse_blocked_us.json")).await;
+
+        let mut channel = channel("UC7A4ikI7Q4Efju5WNRdIqyg");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
         assert!(result.is_ok());
-        
+
         assert_eq!(channel.verification, Some(VerificationStatus::None));
         assert_eq!(channel.conditional_redirect, None);
         assert_eq!(channel.no_index, Some(false));
-        
+
         // Check that US is in blocked countries
-        if let Some(blocked) = channel.blocked_countries {
-            assert!(blocked.contains(&"US".to_string()));
+        if let Some(mut blocked) = channel.blocked_countries {
+            blocked.sort();
+            assert_eq!(blocked, vec!["CA".to_string(), "US".to_string()]);
         } else {
             panic!("Blocked countries should be populated");
         }
@@ -323,65 +340,29 @@ mod tests {
 
     #[tokio::test]
     async fn test_verified_channel() {
-        let client = Client::new();
-        let mut channel = Channel {
-            user_id: "UCewMTclBJZPaNEfbf-qYMGA".to_string(),
-            display_name: None,
-            description: None,
-            handle: None,
-            profile_picture: None,
-            banner: None,
-            created_at: 0,
-            country: None,
-            view_count: 0,
-            subscriber_count: 0,
-            video_count: 0,
-            made_for_kids: false,
-            keywords: None,
-            trailer: None,
-            analytics_account_id: None,
-            conditional_redirect: None,
-            no_index: None,
-            verification: None,
-            blocked_countries: None,
-        };
+        let mock = MockYouTube::start().await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, fixture("innertube/browse_verified.json")).await;
 
-        let result = enrich_channel_data(&client, &mut channel).await;
+        let mut channel = channel("UCBR8-60-B28hp2BmDPdntcQ");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
         assert!(result.is_ok());
-        
+
         assert_eq!(channel.verification, Some(VerificationStatus::Verified));
         assert_eq!(channel.conditional_redirect, None);
         assert_eq!(channel.no_index, Some(false));
+        assert_eq!(channel.blocked_countries, None);
+        assert_eq!(channel.handle.as_deref(), Some("youtube"));
     }
 
     #[tokio::test]
     async fn test_artist_channel() {
-        let client = Client::new();
-        let mut channel = Channel {
-            user_id: "UCsRM0YB_dabtEPGPTKo-gcw".to_string(),
-            display_name: None,
-            description: None,
-            handle: None,
-            profile_picture: None,
-            banner: None,
-            created_at: 0,
-            country: None,
-            view_count: 0,
-            subscriber_count: 0,
-            video_count: 0,
-            made_for_kids: false,
-            keywords: None,
-            trailer: None,
-            analytics_account_id: None,
-            conditional_redirect: None,
-            no_index: None,
-            verification: None,
-            blocked_countries: None,
-        };
+        let mock = MockYouTube::start().await;
+        mock.browse("UCsRM0YB_dabtEPGPTKo-gcw", 200, fixture("innertube/browse_oac.json")).await;
 
-        let result = enrich_channel_data(&client, &mut channel).await;
+        let mut channel = channel("UCsRM0YB_dabtEPGPTKo-gcw");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
         assert!(result.is_ok());
-        
+
         assert_eq!(channel.verification, Some(VerificationStatus::OAC));
         assert_eq!(channel.conditional_redirect, None);
         assert_eq!(channel.no_index, Some(false));
@@ -389,63 +370,61 @@ mod tests {
 
     #[tokio::test]
     async fn test_channel_with_redirect() {
-        let client = Client::new();
-        let mut channel = Channel {
-            user_id: "UC80zzW0g4xuUwW6IffjhcDQ".to_string(),
-            display_name: None,
-            description: None,
-            handle: None,
-            profile_picture: None,
-            banner: None,
-            created_at: 0,
-            country: None,
-            view_count: 0,
-            subscriber_count: 0,
-            video_count: 0,
-            made_for_kids: false,
-            keywords: None,
-            trailer: None,
-            analytics_account_id: None,
-            conditional_redirect: None,
-            no_index: None,
-            verification: None,
-            blocked_countries: None,
-        };
+        let mock = MockYouTube::start().await;
+        mock.browse("UC80zzW0g4xuUwW6IffjhcDQ", 200, fixture("innertube/browse_redirect.json")).await;
 
-        let result = enrich_channel_data(&client, &mut channel).await;
+        let mut channel = channel("UC80zzW0g4xuUwW6IffjhcDQ");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
         assert!(result.is_ok());
-        
-        assert!(channel.conditional_redirect.is_some());
+
+        assert_eq!(channel.conditional_redirect.as_deref(), Some("UCtI6KR_Y7memgBmEW0p8POw"));
+        assert_eq!(channel.verification, None);
     }
 
     #[tokio::test]
     async fn test_channel_with_noindex() {
-        let client = Client::new();
-        let mut channel = Channel {
-            user_id: "UC-8U_MhAZnBXKZvI5kMllLA".to_string(),
-            display_name: None,
-            description: None,
-            handle: None,
-            profile_picture: None,
-            banner: None,
-            created_at: 0,
-            country: None,
-            view_count: 0,
-            subscriber_count: 0,
-            video_count: 0,
-            made_for_kids: false,
-            keywords: None,
-            trailer: None,
-            analytics_account_id: None,
-            conditional_redirect: None,
-            no_index: None,
-            verification: None,
-            blocked_countries: None,
-        };
+        let mock = MockYouTube::start().await;
+        mock.browse("UC-8U_MhAZnBXKZvI5kMllLA", 200, fixture("innertube/browse_noindex.json")).await;
 
-        let result = enrich_channel_data(&client, &mut channel).await;
+        let mut channel = channel("UC-8U_MhAZnBXKZvI5kMllLA");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
         assert!(result.is_ok());
-        
+
         assert_eq!(channel.no_index, Some(true));
+        assert_eq!(channel.verification, Some(VerificationStatus::None));
+    }
+
+    #[tokio::test]
+    async fn test_browse_server_error() {
+        let mock = MockYouTube::start().await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 500, "{}".to_string()).await;
+
+        let mut channel = channel("UCBR8-60-B28hp2BmDPdntcQ");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
+        assert!(matches!(result, Err(YouTubeError::UnknownStatusCode(status)) if status.as_u16() == 500));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_browse_retries_server_error() {
+        let mock = MockYouTube::start().await;
+        mock.sequence("/youtubei/v1/browse", &[
+            (503, "{}".to_string()),
+            (200, fixture("innertube/browse_verified.json")),
+        ]).await;
+
+        let mut channel = channel("UCBR8-60-B28hp2BmDPdntcQ");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
+        assert!(result.is_ok());
+        assert_eq!(channel.verification, Some(VerificationStatus::Verified));
+    }
+
+    #[tokio::test]
+    async fn test_browse_malformed_body() {
+        let mock = MockYouTube::start().await;
+        mock.browse("UCBR8-60-B28hp2BmDPdntcQ", 200, "<!DOCTYPE html>".to_string()).await;
+
+        let mut channel = channel("UCBR8-60-B28hp2BmDPdntcQ");
+        let result = enrich_channel_data(&mock.youtubei_client(), &mut channel).await;
+        assert!(matches!(result, Err(YouTubeError::ParseError(_))));
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Instant;
+use serde::Serialize;
+use tracing::{field, info, info_span, warn, Instrument};
+use crate::errors::YouTubeError;
+use crate::retry::{is_retryable, is_retryable_status};
+use crate::transport::{Transport, UpstreamResponse};
+
+/// `clientVersion` of the WEB client unless configured otherwise
+pub const DEFAULT_CLIENT_VERSION: &str = "2.20250108.06.00";
+
+/// Client for the InnerTube API (`youtubei/v1`) used by youtube.com itself.
+#[derive(Debug, Clone)]
+pub struct YouTubeiClient {
+    transport: Transport,
+    base_url: String,
+    client_version: String,
+}
+
+impl YouTubeiClient {
+    pub fn new(transport: Transport, base_url: impl Into<String>) -> Self {
+        Self {
+            transport,
+            base_url: base_url.into(),
+            client_version: DEFAULT_CLIENT_VERSION.to_string(),
+        }
+    }
+
+    pub fn with_client_version(mut self, client_version: impl Into<String>) -> Self {
+        self.client_version = client_version.into();
+        self
+    }
+
+    pub fn client_version(&self) -> &str {
+        &self.client_version
+    }
+
+    /// POSTs `body` to `endpoint` and returns the raw response. Network errors,
+    /// 429s and 5xx responses are retried according to the endpoint's policy;
+    /// any other status is left to the caller since each InnerTube endpoint
+    /// signals errors differently. Runs inside an `upstream` span like Data
+    /// API calls, without a quota cost.
+    pub async fn post<T: Serialize>(
+        &self,
+        endpoint: &str,
+        host: &str,
+        field_mask: &str,
+        body: &T,
+    ) -> Result<UpstreamResponse, YouTubeError> {
+        let span = info_span!(
+            "upstream",
+            api = "innertube",
+            endpoint,
+            status = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let started = Instant::now();
+        let result = self.send(endpoint, host, field_mask, body).instrument(span.clone()).await;
+
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        match &result {
+            Ok(resp) => {
+                span.record("status", resp.status.as_u16());
+                info!(parent: &span, "InnerTube call finished");
+            }
+            Err(e) => info!(parent: &span, error = %e, "InnerTube call failed"),
+        }
+        result
+    }
+
+    async fn send<T: Serialize>(
+        &self,
+        endpoint: &str,
+        host: &str,
+        field_mask: &str,
+        body: &T,
+    ) -> Result<UpstreamResponse, YouTubeError> {
+        let request = self.transport
+            .client()
+            .post(format!("{}/{}", self.base_url, endpoint))
+            .header("Host", host)
+            .header("X-Goog-Fieldmask", field_mask)
+            .json(body)
+            .build()
+            .map_err(|e| YouTubeError::Other(Box::new(e)))?;
+
+        let policy = self.transport.retry_policy(endpoint);
+        let mut attempt = 1;
+
+        loop {
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| YouTubeError::Other("InnerTube request body is not clonable".into()))?;
+
+            let (delay, result) = match self.transport.send(attempt_request).await {
+                Ok(resp) if is_retryable_status(resp.status) => {
+                    (policy.delay(attempt, Some(&resp.headers)), Ok(resp))
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if is_retryable(&e) => (policy.delay(attempt, None), Err(e)),
+                Err(e) => return Err(e),
+            };
+
+            match delay {
+                Some(delay) => {
+                    warn!(attempt, max_attempts = policy.max_attempts, ?delay, "Retrying InnerTube call");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return result,
+            }
+        }
+    }
+}
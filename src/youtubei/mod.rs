@@ -1,2 +1,3 @@
+pub mod client;
 pub mod browse;
 pub mod resolve_url;
\ No newline at end of file
@@ -1,5 +1,9 @@
+use std::time::Instant;
 use serde::{Serialize, Deserialize};
+use tracing::warn;
 use crate::errors::YouTubeError;
+use crate::telemetry;
+use super::client::YouTubeiClient;
 
 #[derive(Debug, Serialize)]
 struct InnertubeClient {
@@ -49,7 +53,7 @@ struct Response {
     endpoint: EndpointType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ResolveUrlResult {
     BrowseEndpoint {
         browse_id: String,
@@ -59,73 +63,80 @@ pub enum ResolveUrlResult {
     },
 }
 
+/// What a youtube.com URL points at. A URL that points nowhere fails with
+/// `YouTubeError::NotFound`.
 pub async fn resolve_url(
-    client: &reqwest::Client,
+    client: &YouTubeiClient,
     url: String,
-) -> Result<Option<ResolveUrlResult>, YouTubeError> {
+) -> Result<ResolveUrlResult, YouTubeError> {
+    let started = Instant::now();
+    let result = fetch(client, url).await;
+    telemetry::record_upstream("innertube", "resolve_url", started, &result);
+    result
+}
+
+async fn fetch(
+    client: &YouTubeiClient,
+    url: String,
+) -> Result<ResolveUrlResult, YouTubeError> {
     let request = ResolveUrlRequest {
         context: InnertubeContext {
             client: InnertubeClient {
                 client_name: "WEB".to_string(),
-                client_version: "2.20240101".to_string(),
+                client_version: client.client_version().to_string(),
             },
         },
         url,
     };
 
     let resp = client
-        .post("https://www.youtube.com/youtubei/v1/navigation/resolve_url")
-        .header("Host", "youtubei.googleapis.com")
-        .header("X-Goog-Fieldmask", "endpoint(urlEndpoint.url,browseEndpoint.browseId)")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| YouTubeError::Other(Box::new(e)))?;
-
-    match resp.status() {
+        .post(
+            "navigation/resolve_url",
+            "youtubei.googleapis.com",
+            "endpoint(urlEndpoint.url,browseEndpoint.browseId)",
+            &request,
+        )
+        .await?;
+
+    match resp.status {
         reqwest::StatusCode::OK => (),
         reqwest::StatusCode::NOT_FOUND => return Err(YouTubeError::NotFound),
         reqwest::StatusCode::UNAUTHORIZED => return Err(YouTubeError::Unauthorized),
         status => {
-            let body_str = resp
-                .text()
-                .await
-                .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
-            eprintln!("Unknown status code {}: {}", status.as_u16(), body_str);
-            return Err(YouTubeError::UnknownStatusCode(status.into()));
+            warn!(status = status.as_u16(), body = resp.text(), "Unknown resolve_url status code");
+            return Err(YouTubeError::UnknownStatusCode(status));
         }
     }
 
-    let response: Response = resp
-        .json()
-        .await
-        .map_err(|e| YouTubeError::ParseError(e.to_string()))?;
+    let response: Response = resp.json()?;
 
     match response.endpoint {
-        EndpointType::Browse { browse_endpoint } => Ok(Some(ResolveUrlResult::BrowseEndpoint {
+        EndpointType::Browse { browse_endpoint } => Ok(ResolveUrlResult::BrowseEndpoint {
             browse_id: browse_endpoint.browse_id,
-        })),
-        EndpointType::Url { url_endpoint } => Ok(Some(ResolveUrlResult::UrlEndpoint {
+        }),
+        EndpointType::Url { url_endpoint } => Ok(ResolveUrlResult::UrlEndpoint {
             url: url_endpoint.url,
-        })),
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use reqwest::Client;
+    use crate::test_support::{fixture, MockYouTube};
 
     #[tokio::test]
     async fn test_resolve_browse_endpoint() {
-        let client = Client::new();
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/@YouTube", 200, fixture("innertube/resolve_browse.json")).await;
+
         let result = resolve_url(
-            &client,
+            &mock.youtubei_client(),
             "youtube.com/@YouTube".to_string(),
         ).await;
 
         match result {
-            Ok(Some(ResolveUrlResult::BrowseEndpoint { browse_id })) => {
+            Ok(ResolveUrlResult::BrowseEndpoint { browse_id }) => {
                 assert_eq!(browse_id, "UCBR8-60-B28hp2BmDPdntcQ");
             }
             _ => panic!("Expected BrowseEndpoint result"),
@@ -134,17 +145,37 @@ mod tests {
 
     #[tokio::test]
     async fn test_resolve_url_endpoint() {
-        let client = Client::new();
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/NikPMusic", 200, fixture("innertube/resolve_url.json")).await;
+
         let result = resolve_url(
-            &client,
+            &mock.youtubei_client(),
             "youtube.com/NikPMusic".to_string(),
         ).await;
 
         match result {
-            Ok(Some(ResolveUrlResult::UrlEndpoint { url })) => {
+            Ok(ResolveUrlResult::UrlEndpoint { url }) => {
                 assert_eq!(url, "http://www.youtube.com/channel/UCtI6KR_Y7memgBmEW0p8POw");
             }
             _ => panic!("Expected UrlEndpoint result"),
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_resolve_not_found() {
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/+doesnotexist", 404, "{}".to_string()).await;
+
+        let result = resolve_url(&mock.youtubei_client(), "youtube.com/+doesnotexist".to_string()).await;
+        assert!(matches!(result, Err(YouTubeError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_malformed_body() {
+        let mock = MockYouTube::start().await;
+        mock.resolve_url("youtube.com/@YouTube", 200, "{\"endpoint\": {\"watchEndpoint\": {}}}".to_string()).await;
+
+        let result = resolve_url(&mock.youtubei_client(), "youtube.com/@YouTube".to_string()).await;
+        assert!(matches!(result, Err(YouTubeError::ParseError(_))));
+    }
+}